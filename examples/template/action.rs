@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use serde::Serialize;
+use shadotui_template::{split_action, AppAction};
+
+/// The runtime's actions together with the template's.
+pub type Action = shadotui_template::Action<TemplateAction>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum TemplateAction {
+    ToggleShowLogger,
+    ScheduleIncrement(usize),
+    ScheduleDecrement(usize),
+    Increment(usize),
+    Decrement(usize),
+    SetCounter(usize),
+    Notify(String),
+    DismissNotification,
+    Answer(String),
+    CompleteInput(String),
+}
+
+impl AppAction for TemplateAction {
+    fn with_count(self, count: usize) -> Self {
+        match self {
+            Self::ScheduleIncrement(i) => Self::ScheduleIncrement(i.saturating_mul(count)),
+            Self::ScheduleDecrement(i) => Self::ScheduleDecrement(i.saturating_mul(count)),
+            Self::Increment(i) => Self::Increment(i.saturating_mul(count)),
+            Self::Decrement(i) => Self::Decrement(i.saturating_mul(count)),
+            action => action,
+        }
+    }
+}
+
+/// Parses the template's action names, e.g. `Increment(5)`. Amounts default to 1 and
+/// `CompleteInput` takes no argument, the component fills in its current input.
+impl FromStr for TemplateAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = split_action(s)?;
+        let count = || -> Result<usize, Error> {
+            arg.map_or(Ok(1), str::parse)
+                .map_err(|_| anyhow!("invalid amount in action `{s}`"))
+        };
+        let action = match name {
+            "ScheduleIncrement" => return Ok(Self::ScheduleIncrement(count()?)),
+            "ScheduleDecrement" => return Ok(Self::ScheduleDecrement(count()?)),
+            "Increment" => return Ok(Self::Increment(count()?)),
+            "Decrement" => return Ok(Self::Decrement(count()?)),
+            "SetCounter" => {
+                let value = arg.ok_or_else(|| anyhow!("action `{s}` needs a value"))?;
+                let value = value
+                    .parse()
+                    .map_err(|_| anyhow!("invalid value in action `{s}`"))?;
+                return Ok(Self::SetCounter(value));
+            }
+            "Notify" => {
+                let text = arg.ok_or_else(|| anyhow!("action `{s}` needs a text"))?;
+                return Ok(Self::Notify(text.to_string()));
+            }
+            "ToggleShowLogger" => Self::ToggleShowLogger,
+            "DismissNotification" => Self::DismissNotification,
+            "CompleteInput" => Self::CompleteInput(String::new()),
+            _ => return Err(anyhow!("unknown action `{s}`")),
+        };
+        match arg {
+            Some(_) => Err(anyhow!("action `{name}` takes no arguments")),
+            None => Ok(action),
+        }
+    }
+}
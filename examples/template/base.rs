@@ -0,0 +1,848 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::anyhow;
+
+use crossterm::event::{Event, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{
+    prelude::*,
+    widgets::{block::Title, *},
+};
+use shadotui_template::{
+    relative_mouse, Component, Frame, JobContext, Jobs, KeyMap, KeyMatcher, Mode, ModeStack,
+    Placed, Reply, Step, Timers,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    time::{sleep, timeout},
+};
+use tui_input::{backend::crossterm::EventHandler, Input, InputRequest};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    action::{Action, TemplateAction},
+    message::Message,
+    other::Other,
+};
+
+/// How long a notification stays up.
+const NOTIFICATION: Duration = Duration::from_secs(3);
+
+/// How long the host gets to answer a lookup.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What to do with newlines in text pasted into the input field.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum PasteNewlines {
+    /// Remove them and insert the rest of the text.
+    #[default]
+    Strip,
+    /// Insert the text up to the first newline and submit it, dropping the remainder.
+    Submit,
+}
+
+#[derive(Default)]
+pub struct Base {
+    counter: usize,
+    lines: Vec<String>,
+    input: Input,
+    modes: ModeStack,
+    ticker: usize,
+
+    /// Relative to the area the base was rendered into, like the mouse events it receives.
+    input_area: Rect,
+    input_scroll: usize,
+    paste_newlines: PasteNewlines,
+    keys: KeyMatcher<TemplateAction>,
+
+    other: Placed<Other>,
+    show_other: bool,
+    notification: Option<String>,
+    answer: Option<String>,
+    jobs: Jobs<TemplateAction>,
+    dirty: bool,
+    summary: bool,
+
+    action_tx: Option<mpsc::UnboundedSender<Action>>,
+    message_tx: Option<mpsc::UnboundedSender<Message>>,
+    timers: Option<Timers<TemplateAction>>,
+}
+
+impl Base {
+    pub fn new(paste_newlines: PasteNewlines, keymap: KeyMap<TemplateAction>) -> Self {
+        Self {
+            paste_newlines,
+            keys: KeyMatcher::new(keymap),
+            ..Self::default()
+        }
+    }
+
+    /// Leaves the counter in the scrollback instead of the last frame of an inline viewport.
+    pub fn with_summary(mut self, summary: bool) -> Self {
+        self.summary = summary;
+        self
+    }
+
+    fn tick(&mut self) {
+        self.ticker = self.ticker.saturating_add(1);
+        self.dirty = true;
+    }
+
+    fn push_mode(&mut self, mode: Mode) {
+        self.dirty |= self.modes.push(mode);
+    }
+
+    fn pop_mode(&mut self) {
+        self.dirty |= self.modes.pop().is_some();
+    }
+
+    fn leave_mode(&mut self, mode: Mode) {
+        self.dirty |= self.modes.leave(mode);
+    }
+
+    fn schedule_increment(&mut self, i: usize) {
+        self.schedule(format!("Increment by {i}"), TemplateAction::Increment(i));
+    }
+
+    fn schedule_decrement(&mut self, i: usize) {
+        self.schedule(format!("Decrement by {i}"), TemplateAction::Decrement(i));
+    }
+
+    /// Sends `action` from a job that takes 5 seconds, reporting its progress every second.
+    fn schedule(&mut self, label: String, action: TemplateAction) {
+        self.process(label, |job| async move {
+            for second in 0..5 {
+                job.progress(second * 20);
+                sleep(Duration::from_secs(1)).await;
+            }
+            Ok(Some(action.into()))
+        });
+    }
+
+    /// Asks the host about `query` and shows its answer, failing if none arrives in time.
+    fn lookup(&mut self, query: String) {
+        let Some(tx) = self.message_tx.clone() else {
+            return;
+        };
+        self.process(format!("Looking up {query:?}"), |_| async move {
+            let (reply, answer) = Reply::new();
+            tx.send(Message::Lookup(query, reply))
+                .map_err(|_| anyhow!("the host is gone"))?;
+            let answer = timeout(LOOKUP_TIMEOUT, answer)
+                .await
+                .map_err(|_| anyhow!("no answer"))?
+                .map_err(|_| anyhow!("the host dropped the request"))?;
+            Ok(Some(TemplateAction::Answer(answer).into()))
+        });
+    }
+
+    /// Runs `job` in processing mode, which is left once the last job is done.
+    fn process<F, Fut>(&mut self, label: String, job: F)
+    where
+        F: FnOnce(JobContext<TemplateAction>) -> Fut,
+        Fut: Future<Output = anyhow::Result<Option<Action>>> + Send + 'static,
+    {
+        if self.jobs.spawn(label, job).is_some() {
+            self.push_mode(Mode::Processing);
+        }
+    }
+
+    /// Turns resolved key steps into actions. All but the last are sent right away so that they
+    /// keep their order ahead of the returned one.
+    fn handle_steps(&mut self, steps: Vec<Step<TemplateAction>>) -> Option<Action> {
+        let mut actions: Vec<_> = steps
+            .into_iter()
+            .map(|step| match step {
+                Step::Action(Action::App(TemplateAction::CompleteInput(_))) => {
+                    TemplateAction::CompleteInput(self.input.to_string()).into()
+                }
+                Step::Action(Action::Accept(_)) => Action::Accept(self.selection()),
+                Step::Action(action) => action,
+                Step::Key(Mode::Insert, key) => {
+                    self.input.handle_event(&Event::Key(key));
+                    Action::Update
+                }
+                Step::Key(..) => Action::Tick,
+            })
+            .collect();
+        let last = actions.pop();
+        if let Some(tx) = &self.action_tx {
+            for action in actions {
+                // Only closed once the app is done with every action anyway
+                let _ = tx.send(action);
+            }
+        }
+        last
+    }
+
+    /// The piped line the counter points at, or the counter itself without any.
+    fn selection(&self) -> String {
+        match self.lines.len() {
+            0 => self.counter.to_string(),
+            len => self.lines[self.counter % len].clone(),
+        }
+    }
+
+    /// The index of the character of the input drawn at display `column`, wide characters taking
+    /// up two columns.
+    fn char_at(&self, column: usize) -> usize {
+        let mut width = 0;
+        self.input
+            .value()
+            .chars()
+            .take_while(|c| {
+                width += c.width().unwrap_or(0);
+                width <= column
+            })
+            .count()
+    }
+
+    /// Shows `text` in the top right corner for a few seconds, replacing the previous one.
+    fn notify(&mut self, text: String) {
+        self.notification = Some(text);
+        self.dirty = true;
+        if let Some(timers) = &mut self.timers {
+            let dismiss = TemplateAction::DismissNotification;
+            timers.after("notification", NOTIFICATION, dismiss.into());
+        }
+    }
+
+    fn increment(&mut self, i: usize) {
+        self.counter = self.counter.saturating_add(i);
+        self.dirty = true;
+    }
+
+    fn decrement(&mut self, i: usize) {
+        self.counter = self.counter.saturating_sub(i);
+        self.dirty = true;
+    }
+
+    /// Applies the template's own actions, the runtime's are handled in [`Component::dispatch`].
+    fn apply(&mut self, action: TemplateAction) -> Option<Action> {
+        match action {
+            TemplateAction::ToggleShowLogger => {
+                self.show_other = !self.show_other;
+                self.dirty = true;
+            }
+            TemplateAction::ScheduleIncrement(i) => self.schedule_increment(i),
+            TemplateAction::ScheduleDecrement(i) => self.schedule_decrement(i),
+            TemplateAction::Increment(i) => self.increment(i),
+            TemplateAction::Decrement(i) => self.decrement(i),
+            TemplateAction::SetCounter(value) => {
+                self.counter = value;
+                self.dirty = true;
+            }
+            TemplateAction::Notify(text) => self.notify(text),
+            TemplateAction::DismissNotification => {
+                self.dirty |= self.notification.take().is_some();
+            }
+            TemplateAction::CompleteInput(s) => {
+                self.input.reset();
+                self.leave_mode(Mode::Insert);
+                self.lookup(s);
+            }
+            TemplateAction::Answer(answer) => {
+                self.answer = Some(answer);
+                self.dirty = true;
+            }
+        }
+        None
+    }
+}
+
+impl Component<Message, TemplateAction> for Base {
+    fn init(
+        &mut self,
+        tx: UnboundedSender<Action>,
+        message_tx: Option<mpsc::UnboundedSender<Message>>,
+        timers: Timers<TemplateAction>,
+    ) -> anyhow::Result<()> {
+        self.action_tx = Some(tx.clone());
+        self.message_tx = message_tx.clone();
+
+        self.other
+            .init(tx.clone(), message_tx.clone(), timers.scope())?;
+        self.jobs.init(tx, message_tx, timers.scope())?;
+        self.timers = Some(timers);
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent) -> Action {
+        let steps = self.keys.feed(&self.modes, key);
+        self.handle_steps(steps).unwrap_or(Action::Update)
+    }
+
+    fn handle_stdin(&mut self, data: String) -> Action {
+        self.lines = data.lines().map(String::from).collect();
+        Action::Update
+    }
+
+    fn handle_message(&mut self, message: Message) -> Action {
+        match message {
+            Message::Quit => Action::Quit,
+            Message::SetCounter(value) => TemplateAction::SetCounter(value).into(),
+            Message::Notify(text) => TemplateAction::Notify(text).into(),
+            // Another launch's piped lines replace ours
+            Message::Forward { args, stdin } => {
+                if let Some(data) = stdin {
+                    self.handle_stdin(data);
+                }
+                let text = if args.is_empty() {
+                    "Relaunched".into()
+                } else {
+                    format!("Relaunched with {}", args.join(" "))
+                };
+                TemplateAction::Notify(text).into()
+            }
+            _ => Action::Noop,
+        }
+    }
+
+    fn handle_paste(&mut self, text: String) -> Action {
+        if self.modes.current() != Mode::Insert {
+            return Action::Noop;
+        }
+        let (text, submit) = match self.paste_newlines {
+            PasteNewlines::Strip => (text.replace(['\r', '\n'], ""), false),
+            PasteNewlines::Submit => match text.split_once(['\r', '\n']) {
+                Some((line, _)) => (line.to_string(), true),
+                None => (text, false),
+            },
+        };
+        for c in text.chars() {
+            self.input.handle(InputRequest::InsertChar(c));
+        }
+        if submit {
+            TemplateAction::CompleteInput(self.input.to_string()).into()
+        } else {
+            Action::Update
+        }
+    }
+
+    fn route_mouse_to_children(&mut self, mouse: MouseEvent) -> Option<Action> {
+        match self.show_other {
+            true => self.other.route_mouse_events(mouse),
+            false => None,
+        }
+    }
+
+    fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Action {
+        match relative_mouse(mouse, self.input_area) {
+            Some(m) if m.kind == MouseEventKind::Down(MouseButton::Left) => {
+                let column = m.column.saturating_sub(1) as usize + self.input_scroll;
+                self.input
+                    .handle(InputRequest::SetCursor(self.char_at(column)));
+                // Redrawn even when already in insert mode, which entering it again leaves alone
+                self.dirty = true;
+                Action::EnterInsert
+            }
+            _ => Action::Noop,
+        }
+    }
+
+    fn dispatch(&mut self, action: Action) -> Option<Action> {
+        match action {
+            Action::Tick => {
+                self.tick();
+                let steps = self.keys.timeout(&self.modes);
+                return self.handle_steps(steps);
+            }
+            Action::App(action) => return self.apply(action),
+            Action::EnterNormal | Action::PopMode => self.pop_mode(),
+            Action::EnterInsert => self.push_mode(Mode::Insert),
+            Action::EnterProcessing => self.push_mode(Mode::Processing),
+            Action::ExitProcessing => self.leave_mode(Mode::Processing),
+            Action::PushMode(mode) => self.push_mode(mode),
+            Action::JobFinished(..) => {
+                self.jobs.dispatch(action);
+                // Stay in processing until the last job is done
+                if self.modes.contains(Mode::Processing) && !self.jobs.is_running() {
+                    return Some(Action::ExitProcessing);
+                }
+            }
+            Action::JobProgress(..) | Action::CancelJob | Action::FocusNextJob => {
+                return self.jobs.dispatch(action);
+            }
+            // Sent after the input or pending keys changed outside of dispatch
+            Action::Update => self.dirty = true,
+            _ => (),
+        }
+        None
+    }
+
+    fn persist(&mut self) -> anyhow::Result<()> {
+        self.other.persist()
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty || self.other.is_dirty() || self.jobs.is_dirty()
+    }
+
+    fn summary(&self) -> Option<String> {
+        self.summary.then(|| match &self.answer {
+            Some(answer) => format!("Counter: {}, answer: {answer}", self.counter),
+            None => format!("Counter: {}", self.counter),
+        })
+    }
+
+    fn snapshot(&self) -> serde_json::Value {
+        serde_json::json!({
+            "counter": self.counter,
+            "ticker": self.ticker,
+            "modes": self.modes.iter().map(|m| m.name()).collect::<Vec<_>>(),
+            "input": self.input.value(),
+            "selection": self.selection(),
+            "jobs_running": self.jobs.is_running(),
+            "notification": self.notification,
+            "answer": self.answer,
+        })
+    }
+
+    fn render(&mut self, f: &mut Frame<'_>, rect: Rect) {
+        let origin = rect;
+        self.dirty = false;
+        let rect = if self.show_other {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(rect);
+            self.other.render(f, chunks[1]);
+            chunks[0]
+        } else {
+            rect
+        };
+
+        let mut text = format!(
+            "Press j or k to increment or decrement.\n\nCounter: {}\n\nTicker: {}",
+            self.counter, self.ticker
+        );
+        if !self.lines.is_empty() {
+            text += &format!("\n\nLine: {}", self.selection());
+        }
+        if let Some(answer) = &self.answer {
+            text += &format!("\n\nAnswer: {answer}");
+        }
+        let rects = Layout::default()
+            .constraints(
+                [
+                    Constraint::Min(0),
+                    Constraint::Length(self.jobs.height()),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(rect);
+
+        f.render_widget(
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .title("Template")
+                        .title(
+                            Title::from(
+                                self.notification
+                                    .as_deref()
+                                    .unwrap_or_default()
+                                    .bold()
+                                    .yellow(),
+                            )
+                            .alignment(Alignment::Right),
+                        )
+                        .title(
+                            Title::from(self.modes.to_string())
+                                .alignment(Alignment::Left)
+                                .position(block::Position::Bottom),
+                        )
+                        .title(
+                            Title::from(self.keys.pending())
+                                .alignment(Alignment::Right)
+                                .position(block::Position::Bottom),
+                        )
+                        .title_alignment(Alignment::Center)
+                        .borders(Borders::ALL)
+                        .border_style(match self.modes.contains(Mode::Processing) {
+                            true => Style::default().fg(Color::Yellow),
+                            false => Style::default(),
+                        })
+                        .border_type(BorderType::Rounded),
+                )
+                .style(Style::default().fg(Color::Cyan))
+                .alignment(Alignment::Center),
+            rects[0],
+        );
+        self.jobs.render(f, rects[1]);
+        let width = rects[2].width.saturating_sub(3); // keep 2 for borders and 1 for cursor
+        let scroll = self.input.visual_scroll(width as usize);
+        self.input_area = Rect {
+            x: rects[2].x - origin.x,
+            y: rects[2].y - origin.y,
+            ..rects[2]
+        };
+        self.input_scroll = scroll;
+        let input = Paragraph::new(self.input.value())
+            .style(match self.modes.current() {
+                Mode::Insert => Style::default().fg(Color::Yellow),
+                _ => Style::default(),
+            })
+            .scroll((0, scroll as u16))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(Line::from(vec![
+                        Span::raw("Enter Input Mode "),
+                        Span::styled("(Press ", Style::default().fg(Color::DarkGray)),
+                        Span::styled(
+                            "/",
+                            Style::default()
+                                .add_modifier(Modifier::BOLD)
+                                .fg(Color::Gray),
+                        ),
+                        Span::styled(" to start, ", Style::default().fg(Color::DarkGray)),
+                        Span::styled(
+                            "ESC",
+                            Style::default()
+                                .add_modifier(Modifier::BOLD)
+                                .fg(Color::Gray),
+                        ),
+                        Span::styled(" to finish)", Style::default().fg(Color::DarkGray)),
+                    ])),
+            );
+        f.render_widget(input, rects[2]);
+        // Only shown with room for it between the borders, in columns past the scrolled-out text
+        if self.modes.current() == Mode::Insert && rects[2].width > 2 && rects[2].height > 2 {
+            let column = self.input.visual_cursor().saturating_sub(scroll);
+            f.set_cursor(
+                rects[2].x + 1 + column.min(width as usize) as u16,
+                rects[2].y + 1,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use proptest::prelude::*;
+
+    use shadotui_template::{testing, App, Event, Exit};
+
+    use super::*;
+    use crate::KEYMAP;
+
+    type Harness = testing::Harness<Message, TemplateAction, Base>;
+
+    fn base() -> Base {
+        Base::new(PasteNewlines::default(), KeyMap::defaults(KEYMAP).unwrap())
+    }
+
+    /// The lookup the host was sent since the last call.
+    async fn lookup(h: &mut Harness) -> (String, Reply<String>) {
+        h.advance(Duration::ZERO).await;
+        match &h.messages()[..] {
+            [Message::Lookup(query, reply)] => (query.clone(), reply.clone()),
+            messages => panic!("expected a lookup, got {messages:?}"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn submitted_input_is_looked_up_with_the_host() {
+        let mut h = Harness::new(base(), 60, 16).await;
+        h.keys("/hello<Space>world").await;
+        assert!(h.lines().await[14].contains("hello world"));
+
+        h.key(KeyCode::Enter).await;
+        let (query, reply) = lookup(&mut h).await;
+        assert_eq!(query, "hello world");
+        let lines = h.lines().await;
+        assert_eq!(
+            lines[14],
+            "│                                                          │"
+        );
+        assert!(lines[9].starts_with("╰normal > processing─"));
+        assert!(lines[11].contains("Looking up \"hello world\": running"));
+
+        assert!(reply.send("HELLO WORLD".into()));
+        h.advance(Duration::ZERO).await;
+        let lines = h.lines().await;
+        assert!(lines.iter().any(|l| l.contains("Answer: HELLO WORLD")));
+        assert!(lines[9].starts_with("╰normal─"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lookups_time_out_and_can_be_cancelled() {
+        let mut h = Harness::new(base(), 60, 12).await;
+        h.keys("/slow<Enter>").await;
+        let (_, reply) = lookup(&mut h).await;
+        h.advance(LOOKUP_TIMEOUT).await;
+        assert!(h
+            .lines()
+            .await
+            .iter()
+            .any(|l| l.contains("failed after 5s: no answer")));
+        assert!(!reply.send("late".into()));
+
+        h.keys("/cancelled<Enter>").await;
+        let (_, reply) = lookup(&mut h).await;
+        h.keys("<Esc>").await;
+        h.advance(Duration::ZERO).await;
+        assert!(h
+            .lines()
+            .await
+            .iter()
+            .any(|l| l.contains("cancelled after 0s")));
+        assert_eq!(h.root().modes.current(), Mode::Normal);
+        assert!(!reply.send("late".into()));
+        assert!(h.root().answer.is_none());
+    }
+
+    #[tokio::test]
+    async fn enter_accepts_the_piped_line_under_the_counter() {
+        let app = App::builder(base())
+            .with_stdin("first\nsecond\n".into())
+            .build();
+        let mut h = Harness::with_app(app, 60, 12).await;
+        h.action(TemplateAction::Increment(1).into()).await;
+        assert!(h.lines().await.iter().any(|l| l.contains("Line: second")));
+
+        h.keys("<Enter>").await;
+        assert_eq!(h.exit(), Some(Exit::Accept("second".into())));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ticks_follow_the_clock() {
+        let mut h = Harness::new(base(), 60, 12).await;
+        h.advance(Duration::from_millis(999)).await;
+        assert_eq!(h.root().ticker, 0);
+        h.advance(Duration::from_millis(1)).await;
+        assert_eq!(h.root().ticker, 1);
+        h.advance(Duration::from_secs(41)).await;
+        assert_eq!(h.root().ticker, 42);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scheduled_increment_lands_after_five_seconds() {
+        let mut h = Harness::new(base(), 60, 12).await;
+        h.action(TemplateAction::ScheduleIncrement(3).into()).await;
+        assert_eq!(h.root().modes.current(), Mode::Processing);
+
+        h.advance(Duration::from_millis(4999)).await;
+        assert_eq!(h.root().counter, 0);
+        assert!(h
+            .lines()
+            .await
+            .iter()
+            .any(|l| l.contains("Increment by 3: running 4s, 80%")));
+
+        h.advance(Duration::from_millis(1)).await;
+        assert_eq!(h.root().counter, 3);
+        assert_eq!(h.root().modes.current(), Mode::Normal);
+        assert!(h.lines().await.iter().any(|l| l.contains("done in 5s")));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn host_messages_drive_the_ui() {
+        let mut h = Harness::new(base(), 60, 12).await;
+        h.message(Message::SetCounter(42)).await;
+        assert!(h.lines().await[3].contains("Counter: 42"));
+
+        h.message(Message::Notify("Saved".into())).await;
+        assert!(h.lines().await[0].ends_with("Saved╮"));
+        h.advance(NOTIFICATION).await;
+        assert!(!h.lines().await[0].contains("Saved"));
+
+        let args = vec!["-a".into(), "500".into()];
+        let stdin = Some("first\nsecond\n".into());
+        h.message(Message::Forward { args, stdin }).await;
+        assert!(h.lines().await[0].ends_with("Relaunched with -a 500╮"));
+        assert!(h.lines().await.iter().any(|l| l.contains("Line: first")));
+
+        h.message(Message::Quit).await;
+        assert_eq!(h.exit(), Some(Exit::Cancel));
+    }
+
+    #[tokio::test]
+    async fn clicking_the_input_places_the_cursor() {
+        let mut h = Harness::new(base(), 60, 12).await;
+        h.keys("/日本x<Esc>").await;
+        h.render().await;
+
+        let click = |column, row| {
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        // Past both wide characters, before the `x`
+        h.event(click(5, 10)).await;
+        assert_eq!(h.root().modes.current(), Mode::Insert);
+        assert_eq!(h.cursor().await, Some((5, 10)));
+        h.keys("y").await;
+        assert_eq!(h.root().input.value(), "日本yx");
+
+        // Moving the cursor within insert mode is drawn right away
+        h.render().await;
+        h.event(click(1, 10)).await;
+        assert!(h.root().is_dirty());
+        assert_eq!(h.cursor().await, Some((1, 10)));
+
+        h.keys("<Esc>").await;
+        h.event(click(5, 2)).await;
+        assert_eq!(h.root().modes.current(), Mode::Normal);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pasted_newlines_are_stripped_or_submit_the_first_line() {
+        let mut h = Harness::new(base(), 60, 12).await;
+        h.paste("a\nb").await;
+        assert_eq!(h.root().input.value(), "");
+        h.keys("/").await;
+        h.paste("a\nb").await;
+        assert_eq!(h.root().input.value(), "ab");
+
+        let base = Base::new(PasteNewlines::Submit, KeyMap::defaults(KEYMAP).unwrap());
+        let mut h = Harness::new(base, 60, 12).await;
+        h.keys("/").await;
+        h.paste("a\nb").await;
+        let (query, _) = lookup(&mut h).await;
+        assert_eq!(query, "a");
+        assert_eq!(h.root().input.value(), "");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rendering_clears_dirty_without_room_for_the_jobs() {
+        let mut h = Harness::new(base(), 60, 0).await;
+        h.action(TemplateAction::ScheduleIncrement(1).into()).await;
+        assert!(h.root().is_dirty());
+        h.render().await;
+        assert!(!h.root().is_dirty());
+    }
+
+    #[tokio::test]
+    async fn summary_replaces_the_last_frame_when_asked_for() {
+        let mut h = Harness::new(base(), 60, 12).await;
+        h.action(TemplateAction::Increment(3).into()).await;
+        assert_eq!(h.root().summary(), None);
+
+        let base = base().with_summary(true);
+        let mut h = Harness::with_app(App::builder(base).build(), 60, 12).await;
+        h.action(TemplateAction::Increment(3).into()).await;
+        assert_eq!(h.root().summary().as_deref(), Some("Counter: 3"));
+        h.action(TemplateAction::Answer("yes".into()).into()).await;
+        assert_eq!(
+            h.root().summary().as_deref(),
+            Some("Counter: 3, answer: yes")
+        );
+    }
+
+    #[tokio::test]
+    async fn initial_frame() {
+        let mut h = Harness::new(base(), 60, 12).await;
+        h.assert_snapshot("base_initial").await;
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Key(KeyEvent),
+        Paste(String),
+        Resize(u16, u16),
+        Wait(u64),
+    }
+
+    fn key() -> impl Strategy<Value = KeyEvent> {
+        let code = prop_oneof![
+            4 => prop::sample::select(vec!['/', 'j', 'k', 'l', 'q', '5', '0']).prop_map(KeyCode::Char),
+            2 => any::<char>().prop_map(KeyCode::Char),
+            3 => prop::sample::select(vec![
+                KeyCode::Enter,
+                KeyCode::Esc,
+                KeyCode::Tab,
+                KeyCode::Backspace,
+                KeyCode::Delete,
+                KeyCode::Left,
+                KeyCode::Right,
+                KeyCode::Home,
+                KeyCode::End,
+                KeyCode::F(1),
+            ]),
+        ];
+        let modifiers = prop::sample::select(vec![
+            KeyModifiers::NONE,
+            KeyModifiers::SHIFT,
+            KeyModifiers::CONTROL,
+            KeyModifiers::ALT,
+        ]);
+        (code, modifiers).prop_map(|(code, modifiers)| KeyEvent::new(code, modifiers))
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            8 => key().prop_map(Op::Key),
+            1 => "[a-z \r\n\t\u{4e00}-\u{4e0f}]{0,80}".prop_map(Op::Paste),
+            1 => (0..120u16, 0..40u16).prop_map(|(w, h)| Op::Resize(w, h)),
+            1 => (0..3000u64).prop_map(Op::Wait),
+        ]
+    }
+
+    /// Holds after every step, whatever was typed.
+    async fn check_invariants(h: &mut Harness) -> Result<(), TestCaseError> {
+        let cursor = h.cursor().await;
+        let base = h.root();
+        let modes: Vec<_> = base.modes.iter().collect();
+        prop_assert_eq!(modes.last(), Some(&Mode::Normal));
+        for (i, mode) in modes.iter().enumerate() {
+            prop_assert!(!modes[i + 1..].contains(mode), "{} twice", base.modes);
+        }
+        prop_assert_eq!(
+            base.modes.contains(Mode::Processing),
+            base.jobs.is_running(),
+            "processing without jobs or the other way around: {}",
+            base.modes
+        );
+
+        // The root is drawn at the origin, where relative and absolute coordinates agree
+        let input = base.input_area;
+        let inside = input.width > 2 && input.height > 2;
+        match cursor {
+            Some((x, y)) => {
+                prop_assert_eq!(base.modes.current(), Mode::Insert);
+                prop_assert!(
+                    x > input.left() && x < input.right() - 1 && y == input.top() + 1,
+                    "cursor {:?} outside of {:?}",
+                    (x, y),
+                    input
+                );
+            }
+            None => prop_assert!(base.modes.current() != Mode::Insert || !inside),
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn random_input_keeps_invariants(
+            (width, height) in (0..120u16, 0..40u16),
+            ops in prop::collection::vec(op(), 0..60),
+        ) {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .start_paused(true)
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let mut h = Harness::new(base(), width, height).await;
+                check_invariants(&mut h).await?;
+                for op in ops {
+                    match op {
+                        Op::Key(key) => h.event(Event::Key(key)).await,
+                        Op::Paste(text) => h.paste(&text).await,
+                        Op::Resize(w, rows) => h.resize(w, rows).await,
+                        Op::Wait(ms) => h.advance(Duration::from_millis(ms)).await,
+                    }
+                    check_invariants(&mut h).await?;
+                }
+                Ok::<_, TestCaseError>(())
+            })?;
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    io::{IsTerminal, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use shadotui_template::{
+    action_from_words, initialize_panic_handler, runtime_dir, send_action, send_message, version,
+    App, Exit, Instance, KeyMap, Lock, Pacing, Quit, Recorder, Replay, Screen, Target,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    base::{Base, PasteNewlines},
+    message::Message,
+};
+
+mod action;
+mod base;
+mod message;
+mod other;
+
+/// Names the directories of the config and runtime files.
+const APP: &str = env!("CARGO_PKG_NAME");
+/// The template's bindings, merged over the runtime's and under the user's keymap file.
+const KEYMAP: &str = include_str!("keymap.toml");
+
+// Define the command line arguments structure
+#[derive(Parser, Debug)]
+#[command(version = version(env!("CARGO_PKG_VERSION"), clap::crate_authors!()), about = "Shadotui template")]
+struct Args {
+    /// App tick rate
+    #[arg(short, long, default_value_t = 1000)]
+    app_tick_rate: u64,
+    /// Render tick rate
+    #[arg(short, long, default_value_t = 50)]
+    render_tick_rate: u64,
+    /// How newlines in pasted text are handled
+    #[arg(long, value_enum, default_value_t = PasteNewlines::Strip)]
+    paste_newlines: PasteNewlines,
+    /// Keymap file to use instead of the one in the config directory
+    #[arg(short, long)]
+    keymap: Option<PathBuf>,
+    /// Milliseconds a partially typed key sequence waits for its next key
+    #[arg(long, default_value_t = 1000)]
+    key_timeout: u64,
+    /// Log every input event to this file
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    /// Replay the input events logged by `--record` instead of reading the terminal
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+    /// Pacing of `--replay`
+    #[arg(long, value_enum, default_value_t = Pacing::Realtime)]
+    replay_pacing: Pacing,
+    /// Maximum frames drawn per second
+    #[arg(long)]
+    max_fps: Option<u32>,
+    /// Redraw at least every this many milliseconds, even when nothing changed
+    #[arg(long, value_name = "MS")]
+    min_refresh: Option<u64>,
+    /// Draw this many rows below the prompt instead of taking over the whole screen
+    #[arg(long, value_name = "HEIGHT")]
+    inline: Option<u16>,
+    /// Leave a line with the counter in the scrollback in place of the last inline frame
+    #[arg(long, requires = "inline")]
+    summary: bool,
+    /// Where the interface is drawn, `tty` keeps it visible with stdout and stderr redirected
+    #[arg(long, value_enum, default_value_t = Target::Stderr)]
+    output: Target,
+    /// Accept newline-delimited JSON commands on this Unix domain socket instead of the one in the
+    /// runtime directory, which `send` cannot find then
+    #[arg(long, value_name = "PATH")]
+    control: Option<PathBuf>,
+    /// Hand the arguments and piped data to the instance already running, if any, and exit
+    #[arg(long)]
+    single_instance: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Dispatch an action in the running instance, e.g. `send increment 5` or `send quit`
+    Send {
+        /// The action, in keymap notation or as words
+        action: String,
+        /// Its argument, e.g. the amount
+        args: Vec<String>,
+    },
+}
+
+// Main function
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    if let Some(Command::Send { action, args }) = args.command {
+        let response = send_action(&runtime_dir(APP), action_from_words(&action, &args)).await?;
+        println!("{response}");
+        std::process::exit(if response["ok"] == true { 0 } else { 1 });
+    }
+    let stdin = if std::io::stdin().is_terminal() {
+        None
+    } else {
+        Some(std::io::read_to_string(std::io::stdin()).context("Unable to read stdin")?)
+    };
+    // Taken before registering, so that the instance `send` finds stays the one running
+    let mut lock = if args.single_instance {
+        Lock::acquire(&runtime_dir(APP))?
+    } else {
+        None
+    };
+    if args.single_instance && lock.is_none() {
+        // Stdout is left to the running instance's accepted value
+        let args = std::env::args().skip(1).collect();
+        let response = send_message(&runtime_dir(APP), &Message::Forward { args, stdin }).await?;
+        if response["ok"] != true {
+            bail!(
+                "The running instance refused the arguments: {}",
+                response["error"]
+            );
+        }
+        return Ok(());
+    }
+    let screen = args.inline.map_or(Screen::Alternate, Screen::Inline);
+    initialize_panic_handler(screen, args.output);
+
+    let keymap = KeyMap::load(APP, KEYMAP, args.keymap)?
+        .with_timeout(Duration::from_millis(args.key_timeout));
+
+    let (message_tx, mut message_rx) = mpsc::unbounded_channel::<Message>();
+    let (host_tx, host_rx) = mpsc::unbounded_channel::<Message>();
+
+    let base = Base::new(args.paste_newlines, keymap).with_summary(args.summary);
+    let mut app = App::builder(base)
+        .with_tick_rate(args.app_tick_rate, args.render_tick_rate)
+        .with_screen(screen)
+        .with_target(args.output)
+        .with_host(message_tx)
+        .with_messages(host_rx);
+    if let Some(fps) = args.max_fps {
+        app = app.with_max_fps(fps);
+    }
+    if let Some(ms) = args.min_refresh {
+        app = app.with_min_refresh(Duration::from_millis(ms));
+    }
+    if let Some(path) = args.record {
+        app = app.with_recorder(Recorder::create(path)?);
+    }
+    if let Some(path) = args.replay {
+        app = app.with_replay(Replay::load(path, args.replay_pacing)?);
+    }
+    // Found by `send` unless told to listen elsewhere
+    let instance = match args.control {
+        Some(_) => None,
+        None => Instance::register(&runtime_dir(APP))?,
+    };
+    if let Some(path) = args
+        .control
+        .or_else(|| instance.as_ref().map(Instance::socket))
+    {
+        // Where later launches forward their arguments to
+        if let Some(lock) = &mut lock {
+            lock.listen_at(&path)?;
+        }
+        app = app.with_control(path);
+    }
+    if let Some(data) = stdin {
+        app = app.with_stdin(data);
+    }
+    let mut app = app.build();
+    let app = tokio::spawn(async move { app.run().await });
+
+    // The channel closes without a `Quit` when the app fails, its error is returned below
+    while let Some(message) = message_rx.recv().await {
+        match message {
+            // Submitted numbers become the counter, anything else is answered after a while
+            Message::Lookup(query, reply) => match query.trim().parse() {
+                Ok(value) => {
+                    let _ = host_tx.send(Message::SetCounter(value));
+                    reply.send(format!("counter set to {value}"));
+                }
+                Err(_) => {
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_secs(2)).await;
+                        reply.send(query.to_uppercase());
+                    });
+                }
+            },
+            message if message.is_quit() => break,
+            _ => (),
+        }
+    }
+    // The terminal is restored by now, the accepted value goes to whoever reads stdout
+    let exit = app.await??;
+    drop((instance, lock));
+    if let Exit::Accept(value) = &exit {
+        let mut stdout = std::io::stdout();
+        writeln!(stdout, "{value}")?;
+        stdout.flush()?;
+    }
+    std::process::exit(exit.code())
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use shadotui_template::{Quit, Reply};
+
+/// The template's messages, exchanged with the host which receives them through
+/// [`AppBuilder::with_host`] and sends them in through [`AppBuilder::with_messages`] or the control
+/// socket, as e.g. `{"SetCounter":5}`.
+///
+/// [`AppBuilder::with_host`]: shadotui_template::AppBuilder::with_host
+/// [`AppBuilder::with_messages`]: shadotui_template::AppBuilder::with_messages
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Message {
+    None,
+    /// The app quit, or from the host: quit now.
+    Quit,
+    /// Asks the host to look up the submitted text, answered through the reply.
+    #[serde(skip)]
+    Lookup(String, Reply<String>),
+    /// From the host: set the counter to this value.
+    SetCounter(usize),
+    /// From the host: show this text for a few seconds.
+    Notify(String),
+    /// From a second launch in single-instance mode: its arguments and the data piped into it.
+    Forward {
+        args: Vec<String>,
+        stdin: Option<String>,
+    },
+}
+
+impl Quit for Message {
+    fn quit() -> Self {
+        Message::Quit
+    }
+
+    fn is_quit(&self) -> bool {
+        matches!(self, Message::Quit)
+    }
+}
@@ -1,21 +1,31 @@
 use anyhow::Result;
 use ratatui::{prelude::*, widgets::*};
+use shadotui_template::{Component, Frame, Timers};
 use tokio::sync::mpsc::UnboundedSender;
 
-use super::{Action, Component, Frame, Message};
+use crate::{
+    action::{Action, TemplateAction},
+    message::Message,
+};
 
 #[derive(Default)]
-pub struct Other {}
+pub struct Other;
 
-impl Component for Other {
+impl Component<Message, TemplateAction> for Other {
     fn init(
         &mut self,
         _: UnboundedSender<Action>,
         _message_tx: Option<UnboundedSender<Message>>,
+        _timers: Timers<TemplateAction>,
     ) -> Result<()> {
         Ok(())
     }
 
+    /// Always the same, showing it is up to the parent.
+    fn is_dirty(&self) -> bool {
+        false
+    }
+
     fn render(&mut self, f: &mut Frame<'_>, area: Rect) {
         let w = Paragraph::new("HI!").block(
             Block::new()
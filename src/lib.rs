@@ -0,0 +1,7 @@
+//! A runtime for terminal applications built on ratatui: an [`App`] runs a root [`Component`],
+//! feeding it terminal input, ticks and messages from its host as [`Action`]s, and draws it
+//! through a [`TerminalHandler`]. Configure one with [`App::builder`].
+
+mod tui;
+
+pub use tui::*;
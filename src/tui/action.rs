@@ -1,21 +1,117 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Action {
+use std::{fmt::Debug, str::FromStr};
+
+use anyhow::{anyhow, Error};
+use serde::Serialize;
+
+use super::{
+    components::{JobId, JobOutcome},
+    Mode,
+};
+
+/// What the runtime and its components do, carrying the application's own actions of type `A` in
+/// [`Action::App`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Action<A> {
     Quit,
     Resume,
     Suspend,
     Tick,
     RenderTick,
     Resize(u16, u16),
-    ToggleShowLogger,
-    ScheduleIncrement,
-    ScheduleDecrement,
-    Increment(usize),
-    Decrement(usize),
-    CompleteInput(String),
+    Accept(String),
     EnterNormal,
     EnterInsert,
     EnterProcessing,
     ExitProcessing,
+    PushMode(Mode),
+    PopMode,
     Update,
     Noop,
+    JobProgress(JobId, u8),
+    JobFinished(JobId, JobOutcome),
+    CancelJob,
+    FocusNextJob,
+    /// An action of the application, serialized as its own.
+    #[serde(untagged)]
+    App(A),
+}
+
+/// What the runtime needs from the actions an application defines: parsing them from the names
+/// the keymap and the control socket use, and serializing them for control socket subscribers.
+pub trait AppAction:
+    FromStr<Err = Error> + Serialize + Clone + Debug + PartialEq + Send + Sync + 'static
+{
+    /// Applies a count prefix such as the `5` in `5j`. Actions without an amount are unchanged,
+    /// as they are by default.
+    #[allow(unused_variables)]
+    fn with_count(self, count: usize) -> Self {
+        self
+    }
+}
+
+impl<A: AppAction> Action<A> {
+    /// Applies a count prefix to the application's actions, the runtime's have no amount.
+    pub fn with_count(self, count: usize) -> Self {
+        match self {
+            Action::App(action) => Action::App(action.with_count(count)),
+            action => action,
+        }
+    }
+}
+
+impl<A> From<A> for Action<A> {
+    fn from(action: A) -> Self {
+        Action::App(action)
+    }
+}
+
+/// Splits an action in keymap notation such as `Increment(5)` into its name and argument, for
+/// [`AppAction`]s parsing their own.
+pub fn split_action(s: &str) -> Result<(&str, Option<&str>), Error> {
+    match s.split_once('(') {
+        Some((name, rest)) => {
+            let arg = rest
+                .strip_suffix(')')
+                .ok_or_else(|| anyhow!("missing `)` in action `{s}`"))?;
+            Ok((name, Some(arg.trim())))
+        }
+        None => Ok((s, None)),
+    }
+}
+
+/// Parses the action names used in the keymap, e.g. `Quit` or `PushMode(insert)`. Names the
+/// runtime does not know are left to the application's actions. `Accept` takes no argument, the
+/// component fills in its current selection.
+impl<A: AppAction> FromStr for Action<A> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = split_action(s)?;
+        let action = match name {
+            "PushMode" => {
+                let mode = arg.ok_or_else(|| anyhow!("action `{s}` needs a mode"))?;
+                return Ok(Action::PushMode(mode.parse()?));
+            }
+            "Quit" => Action::Quit,
+            "Resume" => Action::Resume,
+            "Suspend" => Action::Suspend,
+            "Tick" => Action::Tick,
+            "RenderTick" => Action::RenderTick,
+            "Accept" => Action::Accept(String::new()),
+            "EnterNormal" => Action::EnterNormal,
+            "EnterInsert" => Action::EnterInsert,
+            "EnterProcessing" => Action::EnterProcessing,
+            "ExitProcessing" => Action::ExitProcessing,
+            "PopMode" => Action::PopMode,
+            "Update" => Action::Update,
+            "Noop" => Action::Noop,
+            "CancelJob" => Action::CancelJob,
+            "FocusNextJob" => Action::FocusNextJob,
+            _ => return s.parse().map(Action::App),
+        };
+        match arg {
+            Some(_) => Err(anyhow!("action `{name}` takes no arguments")),
+            None => Ok(action),
+        }
+    }
 }
@@ -1,74 +1,607 @@
-use std::sync::Arc;
+use std::{
+    any::Any,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Result;
-use tokio::sync::{mpsc, Mutex};
+use futures::{stream::BoxStream, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio::{
+    sync::{broadcast, mpsc, Mutex},
+    task::JoinHandle,
+};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_util::sync::CancellationToken;
 
 use super::{
-    components::{Base, Component},
-    Action, EventHandler, TerminalHandler, Message,
+    components::Component, Action, AppAction, AppMessage, Control, EventHandler, External, Feeds,
+    Inbox, Instant, ParseMessage, Placed, Playback, Recorder, Replay, Screen, ScreenDump, Signals,
+    Sources, Target, Task, TaskError, TerminalHandler, Timers, Tui,
 };
 
-pub struct App {
+/// How many applied actions a slow control socket subscriber may fall behind.
+const EVENT_BACKLOG: usize = 256;
+
+/// How the app was left, see [`App::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exit {
+    /// A component accepted this value, e.g. the selected line.
+    Accept(String),
+    /// The app quit without accepting anything.
+    Cancel,
+}
+
+impl Exit {
+    /// The process exit code, following the convention of shells for interrupted commands on
+    /// cancel so that scripts can tell it apart from errors.
+    pub fn code(&self) -> i32 {
+        match self {
+            Exit::Accept(_) => 0,
+            Exit::Cancel => 130,
+        }
+    }
+}
+
+/// Runs a root component of type `C`, exchanging messages of type `M` with the host and
+/// dispatching the application's actions of type `A`.
+pub struct App<M, A, C> {
     tick_rate: (u64, u64),
     should_quit: bool,
     should_suspend: bool,
+    should_render: bool,
+    last_render: Option<Instant>,
+    frame_interval: Option<Duration>,
+    refresh_interval: Option<Duration>,
+    screen: Screen,
+    target: Target,
+    stdin: Option<String>,
+    accepted: Option<String>,
 
-    base: Arc<Mutex<Base>>,
+    /// Placed so that mouse events reach it relative to where it is drawn, e.g. an inline viewport.
+    root: Arc<Mutex<Placed<C>>>,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    playback: Option<Playback>,
+    sources: Sources,
+    inbox: Inbox<M>,
+    host: Option<mpsc::UnboundedSender<M>>,
+    control: Option<(PathBuf, ParseMessage<M>)>,
+    events: broadcast::Sender<Action<A>>,
+    dump: ScreenDump,
 }
 
-impl App {
-    pub fn new(tick_rate: (u64, u64)) -> Result<Self> {
-        let home = Arc::new(Mutex::new(Base::new()));
-        Ok(Self {
-            tick_rate,
-            base: home,
-            should_quit: false,
-            should_suspend: false,
-        })
+impl<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static> App<M, A, C> {
+    /// Starts configuring an app around `root`, with messages and actions of the application's own
+    /// types.
+    pub fn builder(root: C) -> AppBuilder<M, A, C> {
+        AppBuilder::new(root)
     }
 
-    pub async fn run(&mut self, message_tx: Option<mpsc::UnboundedSender<Message>>) -> Result<()> {
+    /// Checked on every render tick, a frame is drawn when a component is dirty, the terminal was
+    /// resized or resumed, or the minimum refresh interval passed.
+    async fn needs_render(&self) -> bool {
+        let since = self.last_render.map(|t| t.elapsed());
+        match (since, self.frame_interval) {
+            (Some(since), Some(frame)) if since < frame => false,
+            _ => {
+                self.should_render
+                    || matches!((since, self.refresh_interval), (Some(s), Some(r)) if s >= r)
+                    || self.root.lock().await.is_dirty()
+            }
+        }
+    }
+
+    /// Runs until a component quits or accepts a value, restoring the terminal before returning.
+    pub async fn run(&mut self) -> Result<Exit> {
         let (action_tx, mut action_rx) = mpsc::unbounded_channel();
+        let message_tx = self.host.clone();
 
-        self.base.lock().await.init(action_tx.clone(), message_tx.clone())?;
+        let timers = CancellationToken::new();
+        self.init(&action_tx, message_tx.clone(), &timers).await?;
 
-        let mut terminal = TerminalHandler::new(self.base.clone());
-        let mut event = EventHandler::new(self.tick_rate, self.base.clone(), action_tx.clone());
+        let _control = match &self.control {
+            Some((path, parse)) => Some(self.bind_control(path, *parse, &action_tx)?),
+            None => None,
+        };
+        let dump = self.control.as_ref().map(|_| self.dump.clone());
+
+        if let Some(replay) = self.replay.take() {
+            self.playback = Some(Arc::new(Mutex::new(replay.spawn())));
+        }
+        let signals = Signals::new()?;
+        let mut terminal =
+            TerminalHandler::new(self.root.clone(), self.screen, self.target, dump.clone());
+        let mut event = EventHandler::spawn(
+            self.tick_rate,
+            self.root.clone(),
+            action_tx.clone(),
+            self.feeds(&signals),
+        );
+        // The first ticks are a period away, draw right away instead
+        action_tx.send(Action::RenderTick)?;
 
         loop {
-            if let Some(action) = action_rx.recv().await {
+            // A task that ended is noticed before any action that would need it
+            let next = tokio::select! {
+                biased;
+                result = &mut terminal.task => Err(TaskError::new(Task::Terminal, result)),
+                result = &mut event.task => Err(TaskError::new(Task::Event, result)),
+                action = action_rx.recv() => Ok(action),
+            };
+            let action = match next {
+                Ok(action) => action,
+                Err(e) => {
+                    // Stop whichever task is still running
+                    if e.task() == Task::Terminal {
+                        event.stop();
+                        let _ = event.task.await;
+                    } else {
+                        let _ = terminal.stop();
+                        let _ = terminal.task.await;
+                    }
+                    self.fail(e, &signals).await?;
+                    break;
+                }
+            };
+            // Sending to a terminal task that is gone fails, the select reports why on the next turn
+            if let Some(action) = action {
                 match action {
-                    Action::RenderTick => terminal.render()?,
-                    Action::Quit => self.should_quit = true,
-                    Action::Suspend => self.should_suspend = true,
-                    Action::Resume => self.should_suspend = false,
-                    _ => {
-                        if let Some(_action) = self.base.lock().await.dispatch(action) {
-                            action_tx.send(_action)?
+                    Action::RenderTick => {
+                        if self.needs_render().await {
+                            let _ = terminal.render();
+                            self.should_render = false;
+                            self.last_render = Some(Instant::now());
+                        }
+                    }
+                    Action::Resume => {
+                        self.should_suspend = false;
+                        self.should_render = true;
+                        let _ = terminal.resume();
+                    }
+                    action => {
+                        if let Some(action) = self.update(action).await {
+                            action_tx.send(action)?
                         };
                     }
                 }
             }
             if self.should_suspend {
-                terminal.suspend()?;
+                let _ = terminal.suspend();
                 event.stop();
-                terminal.task.await?;
-                event.task.await?;
-                terminal = TerminalHandler::new(self.base.clone());
-                event = EventHandler::new(self.tick_rate, self.base.clone(), action_tx.clone());
+                let suspended = stopped(Task::Terminal, &mut terminal.task).await;
+                let paused = stopped(Task::Event, &mut event.task).await;
+                if let Err(e) = suspended.and(paused) {
+                    self.fail(e, &signals).await?;
+                    break;
+                }
+                terminal =
+                    TerminalHandler::new(self.root.clone(), self.screen, self.target, dump.clone());
+                event = EventHandler::spawn(
+                    self.tick_rate,
+                    self.root.clone(),
+                    action_tx.clone(),
+                    self.feeds(&signals),
+                );
                 action_tx.send(Action::Resume)?;
                 action_tx.send(Action::RenderTick)?;
             } else if self.should_quit {
+                let persisted = self.root.lock().await.persist();
+                timers.cancel();
                 if let Some(tx) = message_tx {
-                    tx.send(Message::Quit).unwrap();
+                    // A host that stopped listening does not need to be told
+                    let _ = tx.send(M::quit());
                 }
-                terminal.stop()?;
+                let _ = terminal.stop();
                 event.stop();
-                terminal.task.await?;
-                event.task.await?;
+                let restored = stopped(Task::Terminal, &mut terminal.task).await;
+                // There is no terminal left to restore after a hangup
+                if !signals.hung_up() {
+                    restored?;
+                }
+                stopped(Task::Event, &mut event.task).await?;
+                self.close_sources().await;
+                persisted?;
                 break;
             }
         }
+        Ok(match self.accepted.take() {
+            Some(value) => Exit::Accept(value),
+            None => Exit::Cancel,
+        })
+    }
+
+    /// Cleans up after `e` stopped the app, once neither task runs any more. Input and output end
+    /// with a hangup, that is the quit it asked for, anything else is returned.
+    async fn fail(&mut self, e: TaskError, signals: &Signals) -> Result<()> {
+        let persisted = self.root.lock().await.persist();
+        // Make sure the terminal is usable
+        let _ = Tui::restore(self.screen, self.target);
+        if signals.hung_up() {
+            return persisted;
+        }
+        Err(e.into())
+    }
+
+    /// Listens on `path` for clients driving the app, with messages parsed by `parse`.
+    pub(super) fn bind_control(
+        &self,
+        path: &Path,
+        parse: ParseMessage<M>,
+        action_tx: &mpsc::UnboundedSender<Action<A>>,
+    ) -> Result<Control> {
+        Control::bind(
+            path,
+            parse,
+            self.root.clone(),
+            action_tx.clone(),
+            self.dump.clone(),
+            self.events.clone(),
+        )
+    }
+
+    /// Shared between the event handlers of a run, so that a suspend loses nothing.
+    fn feeds(&self, signals: &Arc<Signals>) -> Feeds<M> {
+        Feeds {
+            recorder: self.recorder.clone(),
+            replay: self.playback.clone(),
+            sources: self.sources.clone(),
+            inbox: self.inbox.clone(),
+            signals: Some(signals.clone()),
+        }
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    pub(super) fn dump(&self) -> &ScreenDump {
+        &self.dump
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    pub(super) fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    pub(super) fn tick_rate(&self) -> (u64, u64) {
+        self.tick_rate
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    pub(super) fn root(&self) -> &Arc<Mutex<Placed<C>>> {
+        &self.root
+    }
+
+    /// How the app would end if it stopped now, `None` while it keeps running.
+    #[cfg(any(test, feature = "testing"))]
+    pub(super) fn exit(&self) -> Option<Exit> {
+        self.should_quit.then(|| match &self.accepted {
+            Some(value) => Exit::Accept(value.clone()),
+            None => Exit::Cancel,
+        })
+    }
+
+    /// Drops the external sources, once no event handler polls them any more.
+    pub(super) async fn close_sources(&self) {
+        self.sources.lock().await.clear();
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    pub(super) fn sources(&self) -> &Sources {
+        &self.sources
+    }
+
+    /// Hands the components their channels and timers, followed by the piped data.
+    pub(super) async fn init(
+        &mut self,
+        action_tx: &mpsc::UnboundedSender<Action<A>>,
+        message_tx: Option<mpsc::UnboundedSender<M>>,
+        timers: &CancellationToken,
+    ) -> Result<()> {
+        let mut root = self.root.lock().await;
+        root.init(
+            action_tx.clone(),
+            message_tx,
+            Timers::new(action_tx.clone(), timers.child_token()),
+        )?;
+        if let Some(data) = self.stdin.take() {
+            action_tx.send(root.handle_stdin(data))?;
+        }
         Ok(())
     }
+
+    /// Applies every action that does not involve the terminal, returning the one it leads to.
+    pub(super) async fn update(&mut self, action: Action<A>) -> Option<Action<A>> {
+        if !matches!(action, Action::Tick | Action::RenderTick | Action::Noop) {
+            // Nobody subscribed is not an error
+            let _ = self.events.send(action.clone());
+        }
+        match action {
+            Action::Quit => self.should_quit = true,
+            Action::Accept(value) => {
+                self.accepted = Some(value);
+                self.should_quit = true;
+            }
+            Action::Suspend => self.should_suspend = true,
+            Action::Resize(..) => {
+                self.should_render = true;
+                self.root.lock().await.dispatch(action);
+            }
+            action => return self.root.lock().await.dispatch(action),
+        }
+        None
+    }
+}
+
+/// Waits for a task that was asked to stop, which only fails if the task did.
+async fn stopped(task: Task, handle: &mut JoinHandle<Result<()>>) -> Result<(), TaskError> {
+    match handle.await {
+        Ok(Ok(())) => Ok(()),
+        result => Err(TaskError::new(task, result)),
+    }
+}
+
+/// Configures an [`App`]: its root component, tick rates, where it is drawn, the channels to the host
+/// and the optional subsystems.
+pub struct AppBuilder<M, A, C> {
+    app: App<M, A, C>,
+    sources: Vec<BoxStream<'static, External>>,
+}
+
+impl<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static> AppBuilder<M, A, C> {
+    pub fn new(root: C) -> Self {
+        let app = App {
+            tick_rate: (1000, 50),
+            root: Arc::new(Mutex::new(Placed::new(root))),
+            should_quit: false,
+            should_suspend: false,
+            should_render: true,
+            last_render: None,
+            frame_interval: None,
+            refresh_interval: None,
+            screen: Screen::default(),
+            target: Target::default(),
+            stdin: None,
+            accepted: None,
+            recorder: None,
+            replay: None,
+            playback: None,
+            sources: Sources::default(),
+            // Already closed, the host hands in its own with `with_messages`
+            inbox: Arc::new(Mutex::new(mpsc::unbounded_channel().1)),
+            host: None,
+            control: None,
+            events: broadcast::channel(EVENT_BACKLOG).0,
+            dump: ScreenDump::default(),
+        };
+        Self {
+            app,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Sends [`Event::AppTick`](super::Event::AppTick) every `app` and checks whether to draw
+    /// every `render` milliseconds, 1000 and 50 by default.
+    pub fn with_tick_rate(mut self, app: u64, render: u64) -> Self {
+        self.app.tick_rate = (app, render);
+        self
+    }
+
+    /// Draws into an inline viewport instead of the alternate screen.
+    pub fn with_screen(mut self, screen: Screen) -> Self {
+        self.app.screen = screen;
+        self
+    }
+
+    /// Draws to stdout or the controlling terminal instead of stderr.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.app.target = target;
+        self
+    }
+
+    /// Hands data read from stdin to the components once they are initialized.
+    pub fn with_stdin(mut self, data: String) -> Self {
+        self.app.stdin = Some(data);
+        self
+    }
+
+    /// Logs every input event so the session can be replayed later.
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.app.recorder = Some(recorder);
+        self
+    }
+
+    /// Feeds a recorded session through the event handler before reading from the terminal.
+    pub fn with_replay(mut self, replay: Replay) -> Self {
+        self.app.replay = Some(replay);
+        self
+    }
+
+    /// Caps how many frames are drawn per second, however often components become dirty.
+    pub fn with_max_fps(mut self, fps: u32) -> Self {
+        self.app.frame_interval = Some(Duration::from_secs(1) / fps.max(1));
+        self
+    }
+
+    /// Redraws at least this often even when nothing is dirty, for animations.
+    pub fn with_min_refresh(mut self, interval: Duration) -> Self {
+        self.app.refresh_interval = Some(interval);
+        self
+    }
+
+    /// Polls `stream` alongside terminal input, each item reaching the components as an
+    /// [`External`] event. Sources pause while suspended and are dropped on quit.
+    pub fn with_source<T, S>(mut self, stream: S) -> Self
+    where
+        T: Any + Send + Sync,
+        S: Stream<Item = T> + Send + 'static,
+    {
+        self.sources.push(stream.map(External::new).boxed());
+        self
+    }
+
+    /// Like [`AppBuilder::with_source`] for the receiving end of a channel.
+    pub fn with_receiver<T: Any + Send + Sync>(self, rx: mpsc::UnboundedReceiver<T>) -> Self {
+        self.with_source(UnboundedReceiverStream::new(rx))
+    }
+
+    /// Delivers messages sent by the host to [`Component::handle_message`], polled alongside
+    /// terminal input.
+    pub fn with_messages(mut self, rx: mpsc::UnboundedReceiver<M>) -> Self {
+        self.app.inbox = Arc::new(Mutex::new(rx));
+        self
+    }
+
+    /// Sends the app's messages to the host, ending with [`Quit`](super::Quit) once it quits.
+    pub fn with_host(mut self, tx: mpsc::UnboundedSender<M>) -> Self {
+        self.app.host = Some(tx);
+        self
+    }
+
+    pub fn build(mut self) -> App<M, A, C> {
+        self.app.sources = Arc::new(Mutex::new(self.sources.into_iter().collect()));
+        self.app
+    }
+}
+
+impl<M, A, C> AppBuilder<M, A, C>
+where
+    M: AppMessage + DeserializeOwned,
+    A: AppAction,
+    C: Component<M, A> + Send + 'static,
+{
+    /// Opens a Unix domain socket at `path` while running, through which other processes dispatch
+    /// actions, send messages, read the state and the screen and follow what happens.
+    pub fn with_control(mut self, path: impl Into<PathBuf>) -> Self {
+        self.app.control = Some((path.into(), |value| Ok(serde_json::from_value(value)?)));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{layout::Rect, widgets::Paragraph};
+
+    use super::*;
+    use crate::tui::{
+        testing::{Count, Harness, Note},
+        Frame, Quit,
+    };
+
+    #[derive(Clone, Debug)]
+    enum Host {
+        Add(usize),
+        Stop,
+    }
+
+    impl Quit for Host {
+        fn quit() -> Self {
+            Host::Stop
+        }
+
+        fn is_quit(&self) -> bool {
+            matches!(self, Host::Stop)
+        }
+    }
+
+    #[derive(Default)]
+    struct Total(usize);
+
+    impl Component<Host, Count> for Total {
+        fn handle_message(&mut self, message: Host) -> Action<Count> {
+            match message {
+                Host::Add(n) => Count::Increment(n).into(),
+                Host::Stop => Action::Quit,
+            }
+        }
+
+        fn handle_external_events(&mut self, event: External) -> Action<Count> {
+            match event.downcast_ref::<usize>() {
+                Some(n) => Count::Increment(*n).into(),
+                None => Action::Noop,
+            }
+        }
+
+        fn dispatch(&mut self, action: Action<Count>) -> Option<Action<Count>> {
+            if let Action::App(Count::Increment(n)) = action {
+                self.0 += n;
+            }
+            None
+        }
+
+        fn render(&mut self, f: &mut Frame, area: Rect) {
+            f.render_widget(Paragraph::new(format!("Total: {}", self.0)), area);
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_a_root_with_its_own_message_type() {
+        let app = App::builder(Total::default()).build();
+        let mut h = Harness::with_app(app, 20, 1).await;
+        h.message(Host::Add(2)).await;
+        h.message(Host::Add(3)).await;
+        assert_eq!(h.root().0, 5);
+        assert_eq!(h.lines().await, ["Total: 5"]);
+
+        h.message(Host::quit()).await;
+        assert_eq!(h.exit(), Some(Exit::Cancel));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sources_feed_the_root_until_it_quits() {
+        let (tx, rx) = mpsc::unbounded_channel::<usize>();
+        let app = App::builder(Total::default())
+            .with_receiver(rx)
+            .with_source(futures::stream::iter(["ignored"]))
+            .build();
+        let mut h = Harness::with_app(app, 20, 1).await;
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        h.advance(Duration::ZERO).await;
+        assert_eq!(h.root().0, 5);
+
+        h.action(Action::Quit).await;
+        assert!(tx.is_closed());
+    }
+
+    /// Dirty until rendered, or always when it does not say.
+    struct Canvas(Option<bool>);
+
+    impl Component<Note, Count> for Canvas {
+        fn is_dirty(&self) -> bool {
+            self.0.unwrap_or(true)
+        }
+
+        fn render(&mut self, _: &mut Frame, _: Rect) {
+            self.0 = self.0.map(|_| false);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn renders_only_what_changed_at_the_allowed_rate() {
+        let mut app = App::builder(Canvas(Some(false)))
+            .with_max_fps(10)
+            .with_min_refresh(Duration::from_secs(1))
+            .build();
+        // The first frame is always drawn
+        assert!(app.needs_render().await);
+        app.should_render = false;
+        app.last_render = Some(Instant::now());
+        assert!(!app.needs_render().await);
+
+        app.root.lock().await.0 = Some(true);
+        assert!(!app.needs_render().await, "faster than the frame rate");
+        tokio::time::advance(Duration::from_millis(100)).await;
+        assert!(app.needs_render().await);
+
+        app.root.lock().await.0 = Some(false);
+        assert!(!app.needs_render().await);
+        tokio::time::advance(Duration::from_millis(900)).await;
+        assert!(app.needs_render().await, "due for a refresh");
+
+        let mut app = App::builder(Canvas(None)).build();
+        app.should_render = false;
+        app.last_render = Some(Instant::now());
+        assert!(app.needs_render().await);
+    }
 }
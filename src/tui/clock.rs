@@ -0,0 +1,38 @@
+//! The one source of time for ticks, timers, jobs and key timeouts. It is tokio's clock, so a test
+//! started with `#[tokio::test(start_paused = true)]` owns it: time then only passes while every
+//! task waits on it, skipping straight to the next deadline.
+
+use std::time::Duration;
+
+use tokio::time::Interval;
+pub use tokio::time::{interval_at, sleep, sleep_until, Instant};
+
+use super::Event;
+
+/// The app and render ticks, each first due one period from now.
+pub struct Ticks {
+    app: Interval,
+    render: Interval,
+}
+
+impl Ticks {
+    pub fn new(tick_rate: (u64, u64)) -> Self {
+        let every = |ms| {
+            let period = Duration::from_millis(ms);
+            interval_at(Instant::now() + period, period)
+        };
+        Self {
+            app: every(tick_rate.0),
+            render: every(tick_rate.1),
+        }
+    }
+
+    /// Waits for the next tick, [`Event::AppTick`] first when both are due.
+    pub async fn next(&mut self) -> Event {
+        tokio::select! {
+            biased;
+            _ = self.app.tick() => Event::AppTick,
+            _ = self.render.tick() => Event::RenderTick,
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::{collections::VecDeque, future::Future, time::Duration};
+
+use anyhow::Result;
+use ratatui::{prelude::*, widgets::*};
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+
+use super::{Action, AppAction, AppMessage, Component, Frame, Instant, Timers};
+
+/// How many finished jobs stay listed.
+const HISTORY: usize = 20;
+
+pub type JobId = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum JobOutcome {
+    Done,
+    Cancelled,
+    Failed(String),
+}
+
+/// Handed to a running job to report progress and notice cancellation.
+pub struct JobContext<A> {
+    id: JobId,
+    token: CancellationToken,
+    tx: UnboundedSender<Action<A>>,
+}
+
+impl<A> Clone for JobContext<A> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            token: self.token.clone(),
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<A> JobContext<A> {
+    /// Reports how far along the job is, in percent.
+    pub fn progress(&self, percent: u8) {
+        let _ = self.tx.send(Action::JobProgress(self.id, percent.min(100)));
+    }
+
+    /// Jobs are dropped at their next await point once cancelled, this lets them clean up first.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+struct Job {
+    id: JobId,
+    label: String,
+    started: Instant,
+    finished: Option<(Instant, JobOutcome)>,
+    progress: Option<u8>,
+    token: CancellationToken,
+}
+
+impl Job {
+    fn is_running(&self) -> bool {
+        self.finished.is_none()
+    }
+
+    fn duration(&self) -> Duration {
+        match &self.finished {
+            Some((at, _)) => at.duration_since(self.started),
+            None => self.started.elapsed(),
+        }
+    }
+}
+
+/// Registry of background jobs, listing the running ones followed by the most recently finished.
+pub struct Jobs<A> {
+    jobs: VecDeque<Job>,
+    next: JobId,
+    focused: Option<JobId>,
+    dirty: bool,
+    action_tx: Option<UnboundedSender<Action<A>>>,
+}
+
+impl<A> Default for Jobs<A> {
+    fn default() -> Self {
+        Self {
+            jobs: VecDeque::new(),
+            next: 0,
+            focused: None,
+            dirty: false,
+            action_tx: None,
+        }
+    }
+}
+
+impl<A: AppAction> Jobs<A> {
+    /// Runs `job` in the background under `label`. Its action, if any, is sent when it completes,
+    /// followed by [`Action::JobFinished`]. Returns `None` before the component was initialized.
+    pub fn spawn<F, Fut>(&mut self, label: impl Into<String>, job: F) -> Option<JobId>
+    where
+        F: FnOnce(JobContext<A>) -> Fut,
+        Fut: Future<Output = Result<Option<Action<A>>>> + Send + 'static,
+    {
+        let tx = self.action_tx.clone()?;
+        let id = self.next;
+        self.next += 1;
+        let token = CancellationToken::new();
+        let future = job(JobContext {
+            id,
+            token: token.clone(),
+            tx: tx.clone(),
+        });
+
+        let cancelled = token.clone();
+        tokio::spawn(async move {
+            let outcome = tokio::select! {
+                _ = cancelled.cancelled() => JobOutcome::Cancelled,
+                result = future => match result {
+                    Ok(action) => {
+                        if let Some(action) = action {
+                            let _ = tx.send(action);
+                        }
+                        JobOutcome::Done
+                    }
+                    Err(e) => JobOutcome::Failed(format!("{e:#}")),
+                },
+            };
+            let _ = tx.send(Action::JobFinished(id, outcome));
+        });
+
+        self.jobs.push_front(Job {
+            id,
+            label: label.into(),
+            started: Instant::now(),
+            finished: None,
+            progress: None,
+            token,
+        });
+        self.focused = Some(id);
+        self.dirty = true;
+        Some(id)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.jobs.iter().any(Job::is_running)
+    }
+
+    /// Rows the job list needs, borders included, or 0 without any jobs.
+    pub fn height(&self) -> u16 {
+        match self.jobs.len() {
+            0 => 0,
+            n => n.min(5) as u16 + 2,
+        }
+    }
+
+    fn job_mut(&mut self, id: JobId) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|j| j.id == id)
+    }
+
+    /// Moves the focus to the next running job, wrapping around.
+    fn focus_next(&mut self) {
+        let running: Vec<_> = self
+            .jobs
+            .iter()
+            .filter(|j| j.is_running())
+            .map(|j| j.id)
+            .collect();
+        let at = running.iter().position(|id| Some(*id) == self.focused);
+        self.focused = match at {
+            Some(i) => running.get(i + 1).or(running.first()).copied(),
+            None => running.first().copied(),
+        };
+    }
+
+    fn finish(&mut self, id: JobId, outcome: JobOutcome) {
+        if let Some(job) = self.job_mut(id) {
+            job.finished = Some((Instant::now(), outcome));
+        }
+        // Running jobs first, newest first within each group
+        self.jobs
+            .make_contiguous()
+            .sort_by_key(|j| (!j.is_running(), std::cmp::Reverse(j.id)));
+        let finished = self.jobs.iter().filter(|j| !j.is_running()).count();
+        for _ in HISTORY..finished {
+            self.jobs.pop_back();
+        }
+        if self.focused == Some(id) {
+            self.focus_next();
+        }
+    }
+
+    /// Applies the job actions, leaving the others alone.
+    pub fn dispatch(&mut self, action: Action<A>) -> Option<Action<A>> {
+        match action {
+            Action::JobProgress(id, percent) => {
+                if let Some(job) = self.job_mut(id) {
+                    job.progress = Some(percent);
+                }
+            }
+            Action::JobFinished(id, outcome) => self.finish(id, outcome),
+            Action::CancelJob => {
+                let focused = self.focused.and_then(|id| self.job_mut(id));
+                if let Some(job) = focused {
+                    job.token.cancel();
+                }
+            }
+            Action::FocusNextJob => self.focus_next(),
+            _ => return None,
+        }
+        self.dirty = true;
+        None
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn render(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.dirty = false;
+        let items: Vec<_> = self
+            .jobs
+            .iter()
+            .map(|job| {
+                let secs = job.duration().as_secs();
+                let (status, style) = match &job.finished {
+                    None => match job.progress {
+                        Some(p) => (format!("running {secs}s, {p}%"), Style::default()),
+                        None => (format!("running {secs}s"), Style::default()),
+                    },
+                    Some((_, JobOutcome::Done)) => (
+                        format!("done in {secs}s"),
+                        Style::default().fg(Color::Green),
+                    ),
+                    Some((_, JobOutcome::Cancelled)) => (
+                        format!("cancelled after {secs}s"),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Some((_, JobOutcome::Failed(e))) => (
+                        format!("failed after {secs}s: {e}"),
+                        Style::default().fg(Color::Red),
+                    ),
+                };
+                let marker = if Some(job.id) == self.focused {
+                    "> "
+                } else {
+                    "  "
+                };
+                ListItem::new(format!("{marker}#{} {}: {status}", job.id, job.label)).style(style)
+            })
+            .collect();
+        f.render_widget(
+            List::new(items).block(
+                Block::default()
+                    .title("Jobs")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            ),
+            area,
+        );
+    }
+}
+
+impl<M: AppMessage, A: AppAction> Component<M, A> for Jobs<A> {
+    fn init(
+        &mut self,
+        tx: UnboundedSender<Action<A>>,
+        _message_tx: Option<UnboundedSender<M>>,
+        _timers: Timers<A>,
+    ) -> Result<()> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn dispatch(&mut self, action: Action<A>) -> Option<Action<A>> {
+        Jobs::dispatch(self, action)
+    }
+
+    fn is_dirty(&self) -> bool {
+        Jobs::is_dirty(self)
+    }
+
+    fn render(&mut self, f: &mut Frame<'_>, area: Rect) {
+        Jobs::render(self, f, area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::{
+        sleep,
+        testing::{Count, Harness, Note},
+    };
+
+    type JobHarness = Harness<Note, Count, Jobs<Count>>;
+
+    #[tokio::test(start_paused = true)]
+    async fn jobs_report_progress_and_finish_or_get_cancelled() {
+        let mut h = JobHarness::new(Jobs::default(), 50, 6).await;
+        h.root().spawn("slow", |cx| async move {
+            sleep(Duration::from_secs(60)).await;
+            cx.progress(100);
+            Ok(None)
+        });
+        h.root().spawn("quick", |cx| async move {
+            cx.progress(150);
+            sleep(Duration::from_secs(2)).await;
+            Ok(Some(Count::Increment(1).into()))
+        });
+        h.advance(Duration::from_secs(1)).await;
+        assert!(h.root().is_running());
+        assert!(h.lines().await[1].contains("> #1 quick: running 1s, 100%"));
+
+        h.advance(Duration::from_secs(1)).await;
+        let lines = h.lines().await;
+        assert!(lines[1].contains("> #0 slow: running 2s"));
+        assert!(lines[2].contains("  #1 quick: done in 2s"));
+
+        h.action(Action::CancelJob).await;
+        h.advance(Duration::ZERO).await;
+        assert!(!h.root().is_running());
+        assert!(h.lines().await[1].contains("#1 quick: done in 2s"));
+        assert!(h.lines().await[2].contains("#0 slow: cancelled after 2s"));
+    }
+}
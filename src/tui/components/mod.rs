@@ -3,46 +3,134 @@ use crossterm::event::{KeyEvent, MouseEvent};
 use ratatui::layout::Rect;
 use tokio::sync::mpsc::UnboundedSender;
 
-use super::{Action, Event, Frame, Message};
+use super::{Action, AppAction, AppMessage, Event, External, Frame, Instant, Timers};
 
-pub use base::Base;
-use other::Other;
+pub use jobs::{JobContext, JobId, JobOutcome, Jobs};
+pub use placed::Placed;
 
-mod base;
-mod other;
+mod jobs;
+mod placed;
 
-pub trait Component {
+/// A part of the interface, exchanging messages of type `M` with the host and dispatching the
+/// application's actions of type `A` alongside the runtime's.
+pub trait Component<M: AppMessage, A: AppAction> {
+    /// Hands the component its channels and a [`Timers`] service, which cancels its timers once
+    /// the component is dropped.
     #[allow(unused_variables)]
     fn init(
         &mut self,
-        tx: UnboundedSender<Action>,
-        message_tx: Option<UnboundedSender<Message>>,
+        tx: UnboundedSender<Action<A>>,
+        message_tx: Option<UnboundedSender<M>>,
+        timers: Timers<A>,
     ) -> Result<()> {
         Ok(())
     }
 
-    fn handle_events(&mut self, event: Option<Event>) -> Action {
+    fn handle_events(&mut self, event: Option<Event>) -> Action<A> {
         match event {
             Some(Event::Quit) => Action::Quit,
+            Some(Event::Resume) => Action::Resume,
             Some(Event::AppTick) => Action::Tick,
             Some(Event::RenderTick) => Action::RenderTick,
             Some(Event::Key(key_event)) => self.handle_key_events(key_event),
-            Some(Event::Mouse(mouse_event)) => self.handle_mouse_events(mouse_event),
+            Some(Event::Mouse(mouse_event)) => {
+                self.route_mouse_events(mouse_event).unwrap_or(Action::Noop)
+            }
+            Some(Event::Paste(s)) => self.handle_paste(s),
             Some(Event::Resize(x, y)) => Action::Resize(x, y),
+            Some(Event::External(external)) => self.handle_external_events(external),
             Some(_) | None => Action::Noop,
         }
     }
     #[allow(unused_variables)]
-    fn handle_key_events(&mut self, key: KeyEvent) -> Action {
+    fn handle_key_events(&mut self, key: KeyEvent) -> Action<A> {
         Action::Noop
     }
+    /// Receives the mouse events under [`Component::area`] that no child took, with coordinates
+    /// relative to that area.
     #[allow(unused_variables)]
-    fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Action {
+    fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Action<A> {
         Action::Noop
     }
+    /// Called once per bracketed paste with the whole pasted text.
     #[allow(unused_variables)]
-    fn dispatch(&mut self, action: Action) -> Option<Action> {
+    fn handle_paste(&mut self, text: String) -> Action<A> {
+        Action::Noop
+    }
+    /// Receives the data piped into the app, once at startup.
+    #[allow(unused_variables)]
+    fn handle_stdin(&mut self, data: String) -> Action<A> {
+        Action::Noop
+    }
+    /// Turns an event from a source registered on the [`App`](super::App) into an action.
+    #[allow(unused_variables)]
+    fn handle_external_events(&mut self, event: External) -> Action<A> {
+        Action::Noop
+    }
+    /// Turns a message from the host into an action. Only the [quit](super::Quit) message is
+    /// understood by default.
+    fn handle_message(&mut self, message: M) -> Action<A> {
+        match message.is_quit() {
+            true => Action::Quit,
+            false => Action::Noop,
+        }
+    }
+    /// Offers a mouse event with absolute coordinates to the children, returning the action of the
+    /// one under the pointer. Children kept in a [`Placed`] are routed with
+    /// [`Component::route_mouse_events`].
+    #[allow(unused_variables)]
+    fn route_mouse_to_children(&mut self, mouse: MouseEvent) -> Option<Action<A>> {
         None
     }
+    /// Delivers a mouse event with absolute coordinates to this component if it falls within
+    /// [`Component::area`]: to the children first, then to [`Component::handle_mouse_events`].
+    fn route_mouse_events(&mut self, mouse: MouseEvent) -> Option<Action<A>> {
+        let relative = match self.area() {
+            Some(area) => relative_mouse(mouse, area)?,
+            None => mouse,
+        };
+        self.route_mouse_to_children(mouse)
+            .or_else(|| Some(self.handle_mouse_events(relative)))
+    }
+    /// The area this component was last rendered into, recorded by [`Placed`]. `None` if it
+    /// accepts events anywhere.
+    fn area(&self) -> Option<Rect> {
+        None
+    }
+    #[allow(unused_variables)]
+    fn dispatch(&mut self, action: Action<A>) -> Option<Action<A>> {
+        None
+    }
+    /// Called once before the terminal is restored on quit, whatever caused it.
+    fn persist(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Whether the component changed since it was last rendered. Rendering clears the flag.
+    /// Components that do not track their changes are redrawn on every render tick.
+    fn is_dirty(&self) -> bool {
+        true
+    }
+    /// A line left in the scrollback in place of the last frame when quitting an inline viewport.
+    fn summary(&self) -> Option<String> {
+        None
+    }
+    /// The state reported to control socket clients asking for it.
+    fn snapshot(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
     fn render(&mut self, f: &mut Frame, area: Rect);
 }
+
+/// Translates `mouse` so that its coordinates are relative to `area`, or `None` if the pointer lies
+/// outside of it.
+pub fn relative_mouse(mouse: MouseEvent, area: Rect) -> Option<MouseEvent> {
+    let inside = mouse.column >= area.x
+        && mouse.column < area.right()
+        && mouse.row >= area.y
+        && mouse.row < area.bottom();
+    inside.then(|| MouseEvent {
+        column: mouse.column - area.x,
+        row: mouse.row - area.y,
+        ..mouse
+    })
+}
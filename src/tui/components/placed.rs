@@ -0,0 +1,199 @@
+use std::ops::{Deref, DerefMut};
+
+use anyhow::Result;
+use crossterm::event::{KeyEvent, MouseEvent};
+use ratatui::layout::Rect;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{
+    relative_mouse, Action, AppAction, AppMessage, Component, Event, External, Frame, Timers,
+};
+
+/// A component together with the area it was last rendered into, so that mouse events reach it
+/// only under the pointer and relative to that area. The app places its root this way, components
+/// place their children by keeping them in a `Placed` and rendering them through it.
+#[derive(Default)]
+pub struct Placed<C> {
+    component: C,
+    area: Option<Rect>,
+}
+
+impl<C> Placed<C> {
+    pub fn new(component: C) -> Self {
+        Self {
+            component,
+            area: None,
+        }
+    }
+
+    pub fn into_inner(self) -> C {
+        self.component
+    }
+}
+
+impl<C> Deref for Placed<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.component
+    }
+}
+
+impl<C> DerefMut for Placed<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.component
+    }
+}
+
+/// Forwards everything to the component, except that mouse events are hit-tested against the
+/// recorded area. Nothing is under the pointer before the first render.
+impl<M: AppMessage, A: AppAction, C: Component<M, A>> Component<M, A> for Placed<C> {
+    fn init(
+        &mut self,
+        tx: UnboundedSender<Action<A>>,
+        message_tx: Option<UnboundedSender<M>>,
+        timers: Timers<A>,
+    ) -> Result<()> {
+        self.component.init(tx, message_tx, timers)
+    }
+
+    fn handle_events(&mut self, event: Option<Event>) -> Action<A> {
+        match event {
+            Some(Event::Mouse(mouse)) => self.route_mouse_events(mouse).unwrap_or(Action::Noop),
+            event => self.component.handle_events(event),
+        }
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent) -> Action<A> {
+        self.component.handle_key_events(key)
+    }
+
+    fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Action<A> {
+        self.component.handle_mouse_events(mouse)
+    }
+
+    fn handle_paste(&mut self, text: String) -> Action<A> {
+        self.component.handle_paste(text)
+    }
+
+    fn handle_stdin(&mut self, data: String) -> Action<A> {
+        self.component.handle_stdin(data)
+    }
+
+    fn handle_external_events(&mut self, event: External) -> Action<A> {
+        self.component.handle_external_events(event)
+    }
+
+    fn handle_message(&mut self, message: M) -> Action<A> {
+        self.component.handle_message(message)
+    }
+
+    fn route_mouse_to_children(&mut self, mouse: MouseEvent) -> Option<Action<A>> {
+        self.component.route_mouse_to_children(mouse)
+    }
+
+    fn route_mouse_events(&mut self, mouse: MouseEvent) -> Option<Action<A>> {
+        // Not drawn yet, so not under the pointer either
+        let relative = relative_mouse(mouse, self.area?)?;
+        self.route_mouse_to_children(mouse)
+            .or_else(|| Some(self.handle_mouse_events(relative)))
+    }
+
+    fn area(&self) -> Option<Rect> {
+        self.area
+    }
+
+    fn dispatch(&mut self, action: Action<A>) -> Option<Action<A>> {
+        self.component.dispatch(action)
+    }
+
+    fn persist(&mut self) -> Result<()> {
+        self.component.persist()
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.component.is_dirty()
+    }
+
+    fn summary(&self) -> Option<String> {
+        self.component.summary()
+    }
+
+    fn snapshot(&self) -> serde_json::Value {
+        self.component.snapshot()
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect) {
+        self.area = Some(area);
+        self.component.render(f, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyModifiers, MouseButton, MouseEventKind};
+
+    use super::*;
+    use crate::tui::testing::{Count, Harness, Note};
+
+    /// Remembers the mouse events it was handed.
+    #[derive(Default)]
+    struct Probe(Vec<(u16, u16)>);
+
+    impl Component<Note, Count> for Probe {
+        fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Action<Count> {
+            self.0.push((mouse.column, mouse.row));
+            Action::Noop
+        }
+
+        fn render(&mut self, _: &mut Frame, _: Rect) {}
+    }
+
+    /// Draws its child a little off the origin.
+    #[derive(Default)]
+    struct Parent {
+        child: Placed<Probe>,
+        clicks: Vec<(u16, u16)>,
+    }
+
+    impl Component<Note, Count> for Parent {
+        fn route_mouse_to_children(&mut self, mouse: MouseEvent) -> Option<Action<Count>> {
+            self.child.route_mouse_events(mouse)
+        }
+
+        fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Action<Count> {
+            self.clicks.push((mouse.column, mouse.row));
+            Action::Noop
+        }
+
+        fn render(&mut self, f: &mut Frame, area: Rect) {
+            self.child
+                .render(f, Rect::new(area.x + 10, area.y + 2, 20, 4));
+        }
+    }
+
+    fn click(column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[tokio::test]
+    async fn mouse_events_reach_the_component_under_the_pointer_relative_to_it() {
+        let mut h = Harness::new(Parent::default(), 40, 10).await;
+        // Nothing is anywhere before the first frame
+        h.event(click(12, 3)).await;
+        assert!(h.root().clicks.is_empty());
+
+        h.render().await;
+        h.event(click(12, 3)).await;
+        h.event(click(29, 5)).await;
+        h.event(click(30, 5)).await;
+        assert_eq!(h.root().child.0, [(2, 1), (19, 3)]);
+        assert_eq!(h.root().clicks, [(30, 5)]);
+        assert_eq!(h.root().child.area(), Some(Rect::new(10, 2, 20, 4)));
+    }
+}
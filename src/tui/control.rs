@@ -0,0 +1,299 @@
+//! An optional Unix domain socket to drive a running app from other processes. Clients write one
+//! JSON request per line and get one JSON line back, `{"ok":true,...}` or
+//! `{"ok":false,"error":"..."}`:
+//!
+//! ```text
+//! {"cmd":"action","action":"Increment(5)"}
+//! {"cmd":"message","message":{"Notify":"hi"}}
+//! {"cmd":"state"}
+//! {"cmd":"screen"}
+//! {"cmd":"subscribe"}
+//! ```
+//!
+//! After `subscribe` every action the app applies follows as its JSON form, e.g.
+//! `{"event":{"Increment":5}}` or `{"event":"Quit"}`.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex as SyncMutex},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ratatui::buffer::Buffer;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{broadcast, mpsc, Mutex},
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{components::Component, Action, AppAction, AppMessage};
+
+/// The text of the last frame drawn, one string per row, kept up to date while a control socket is
+/// open.
+pub type ScreenDump = Arc<SyncMutex<Vec<String>>>;
+
+/// Turns the `message` of a request into the app's message type.
+pub type ParseMessage<M> = fn(Value) -> Result<M>;
+
+/// The rows of `buffer` without trailing spaces.
+pub fn buffer_lines(buffer: &Buffer) -> Vec<String> {
+    let width = buffer.area.width.max(1) as usize;
+    buffer
+        .content
+        .chunks(width)
+        .map(|row| {
+            let line: String = row.iter().map(|cell| cell.symbol.as_str()).collect();
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    /// An action in keymap notation.
+    Action {
+        action: String,
+    },
+    /// Handed to the root component as if the host had sent it.
+    Message {
+        message: Value,
+    },
+    State,
+    Screen,
+    Subscribe,
+}
+
+/// What a connection needs from the running app.
+struct Shared<M, A, C> {
+    root: Arc<Mutex<C>>,
+    parse: ParseMessage<M>,
+    action_tx: mpsc::UnboundedSender<Action<A>>,
+    screen: ScreenDump,
+    events: broadcast::Sender<Action<A>>,
+}
+
+impl<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static> Shared<M, A, C> {
+    async fn handle(
+        &self,
+        line: &str,
+        events: &mut Option<broadcast::Receiver<Action<A>>>,
+    ) -> Value {
+        match self.respond(line, events).await {
+            Ok(Value::Null) => json!({ "ok": true }),
+            Ok(Value::Object(mut fields)) => {
+                fields.insert("ok".into(), true.into());
+                fields.into()
+            }
+            Ok(value) => json!({ "ok": true, "value": value }),
+            Err(e) => json!({ "ok": false, "error": format!("{e:#}") }),
+        }
+    }
+
+    async fn respond(
+        &self,
+        line: &str,
+        events: &mut Option<broadcast::Receiver<Action<A>>>,
+    ) -> Result<Value> {
+        let request: Request = serde_json::from_str(line).context("Invalid request")?;
+        Ok(match request {
+            Request::Action { action } => {
+                self.action_tx.send(action.parse()?)?;
+                Value::Null
+            }
+            Request::Message { message } => {
+                let message = (self.parse)(message).context("Invalid message")?;
+                let action = self.root.lock().await.handle_message(message);
+                self.action_tx.send(action)?;
+                Value::Null
+            }
+            Request::State => json!({ "state": self.root.lock().await.snapshot() }),
+            Request::Screen => {
+                let screen = self.screen.lock().map_err(|_| anyhow!("Screen poisoned"))?;
+                json!({ "screen": *screen })
+            }
+            Request::Subscribe => {
+                *events = Some(self.events.subscribe());
+                Value::Null
+            }
+        })
+    }
+
+    async fn serve(&self, stream: UnixStream, token: CancellationToken) -> Result<()> {
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let mut events: Option<broadcast::Receiver<Action<A>>> = None;
+        loop {
+            let next = async {
+                match &mut events {
+                    Some(rx) => Some(rx.recv().await),
+                    None => std::future::pending().await,
+                }
+            };
+            let response = tokio::select! {
+                _ = token.cancelled() => break,
+                line = lines.next_line() => match line? {
+                    Some(line) if line.trim().is_empty() => continue,
+                    Some(line) => self.handle(&line, &mut events).await,
+                    None => break,
+                },
+                Some(event) = next => match event {
+                    Ok(event) => json!({ "event": event }),
+                    Err(broadcast::error::RecvError::Lagged(n)) => json!({ "lagged": n }),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            };
+            let mut line = serde_json::to_vec(&response)?;
+            line.push(b'\n');
+            write.write_all(&line).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A listening control socket, closed and removed when dropped.
+pub struct Control {
+    path: PathBuf,
+    token: CancellationToken,
+}
+
+impl Control {
+    pub fn bind<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static>(
+        path: &Path,
+        parse: ParseMessage<M>,
+        root: Arc<Mutex<C>>,
+        action_tx: mpsc::UnboundedSender<Action<A>>,
+        screen: ScreenDump,
+        events: broadcast::Sender<Action<A>>,
+    ) -> Result<Self> {
+        if path.exists() {
+            // Left behind by an instance that did not exit cleanly, unless something still answers
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                bail!("{} is used by a running instance", path.display());
+            }
+            std::fs::remove_file(path)
+                .with_context(|| format!("Unable to remove stale socket {}", path.display()))?;
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("Unable to listen on {}", path.display()))?;
+
+        let shared = Arc::new(Shared {
+            root,
+            parse,
+            action_tx,
+            screen,
+            events,
+        });
+        let token = CancellationToken::new();
+        let cancelled = token.clone();
+        tokio::spawn(async move {
+            loop {
+                let stream = tokio::select! {
+                    _ = cancelled.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(_) => continue,
+                    },
+                };
+                let (shared, token) = (shared.clone(), cancelled.child_token());
+                tokio::spawn(async move {
+                    // A client going away mid-request only ends its own connection
+                    let _ = shared.serve(stream, token).await;
+                });
+            }
+        });
+        Ok(Self {
+            path: path.to_path_buf(),
+            token,
+        })
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        self.token.cancel();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::Lines, net::unix::OwnedWriteHalf};
+
+    use super::*;
+    use crate::tui::testing::{Counter, Harness};
+
+    struct Client {
+        lines: Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+        write: OwnedWriteHalf,
+    }
+
+    impl Client {
+        async fn connect(path: &Path) -> Self {
+            let (read, write) = UnixStream::connect(path).await.unwrap().into_split();
+            let lines = BufReader::new(read).lines();
+            Self { lines, write }
+        }
+
+        async fn send(&mut self, request: &str) -> Value {
+            self.write.write_all(request.as_bytes()).await.unwrap();
+            self.write.write_all(b"\n").await.unwrap();
+            self.next().await
+        }
+
+        async fn next(&mut self) -> Value {
+            let line = self.lines.next_line().await.unwrap().unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn clients_drive_the_app_over_the_socket() {
+        let path = std::env::temp_dir().join(format!("shadotui-test-{}.sock", std::process::id()));
+        let mut h = Harness::new(Counter::default(), 60, 12).await;
+        let control = h.control(&path).unwrap();
+        let mut client = Client::connect(&path).await;
+
+        assert_eq!(
+            client.send(r#"{"cmd":"subscribe"}"#).await,
+            json!({ "ok": true })
+        );
+        let sent = client
+            .send(r#"{"cmd":"action","action":"Increment(5)"}"#)
+            .await;
+        assert_eq!(sent, json!({ "ok": true }));
+        h.settle().await;
+        assert_eq!(client.next().await, json!({ "event": { "Increment": 5 } }));
+
+        let state = client.send(r#"{"cmd":"state"}"#).await;
+        assert_eq!(state["ok"], true);
+        assert_eq!(state["state"]["counter"], 5);
+        assert_eq!(state["state"]["modes"], json!(["normal"]));
+
+        let sent = client
+            .send(r#"{"cmd":"message","message":{"Notify":"from afar"}}"#)
+            .await;
+        assert_eq!(sent, json!({ "ok": true }));
+        h.settle().await;
+        assert_eq!(
+            client.next().await,
+            json!({ "event": { "Notify": "from afar" } })
+        );
+        h.render().await;
+        let screen = client.send(r#"{"cmd":"screen"}"#).await;
+        let screen: Vec<String> = serde_json::from_value(screen["screen"].clone()).unwrap();
+        assert!(screen.iter().any(|l| l.contains("from afar")));
+        assert!(screen.iter().any(|l| l.contains("Counter: 5")));
+
+        let failed = client.send(r#"{"cmd":"action","action":"Bogus"}"#).await;
+        assert_eq!(failed["ok"], false);
+        let failed = client.send("not json").await;
+        assert_eq!(failed["ok"], false);
+
+        drop(control);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+use tokio::task::JoinError;
+
+/// One of the tasks the [`App`](super::App) runs alongside its action loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    Terminal,
+    Event,
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Task::Terminal => f.write_str("terminal task"),
+            Task::Event => f.write_str("event task"),
+        }
+    }
+}
+
+/// A task stopped while the app was still running. The terminal has already been restored when
+/// this reaches the caller of [`App::run`](super::App::run).
+#[derive(Debug)]
+pub enum TaskError {
+    /// The task returned an error.
+    Failed(Task, anyhow::Error),
+    /// The task panicked or was aborted.
+    Aborted(Task, JoinError),
+    /// The task returned without being asked to stop.
+    Exited(Task),
+}
+
+impl TaskError {
+    pub fn new(task: Task, result: Result<anyhow::Result<()>, JoinError>) -> Self {
+        match result {
+            Ok(Ok(())) => TaskError::Exited(task),
+            Ok(Err(e)) => TaskError::Failed(task, e),
+            Err(e) => TaskError::Aborted(task, e),
+        }
+    }
+
+    pub fn task(&self) -> Task {
+        match self {
+            TaskError::Failed(task, _) | TaskError::Aborted(task, _) | TaskError::Exited(task) => {
+                *task
+            }
+        }
+    }
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::Failed(task, _) => write!(f, "{task} failed"),
+            TaskError::Aborted(task, _) => write!(f, "{task} aborted"),
+            TaskError::Exited(task) => write!(f, "{task} exited unexpectedly"),
+        }
+    }
+}
+
+impl std::error::Error for TaskError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TaskError::Failed(_, e) => Some(e.as_ref()),
+            TaskError::Aborted(_, e) => Some(e),
+            TaskError::Exited(_) => None,
+        }
+    }
+}
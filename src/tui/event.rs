@@ -1,7 +1,12 @@
-use std::{sync::Arc, time::Duration};
+use std::{any::Any, fmt, sync::Arc};
 
+use anyhow::{bail, Context, Result};
 use crossterm::event::{Event as CrosstermEvent, KeyEvent, KeyEventKind, MouseEvent};
-use futures::{FutureExt, StreamExt};
+use futures::{
+    stream::{BoxStream, SelectAll},
+    FutureExt, StreamExt,
+};
+use serde::{Deserialize, Serialize};
 use tokio::{
     sync::{mpsc, Mutex},
     task::JoinHandle,
@@ -10,90 +15,190 @@ use tokio_util::sync::CancellationToken;
 
 use super::{
     Action,
-    components::{Base, Component},
+    components::Component,
+    AppAction, AppMessage, Recorder, Signals, Ticks,
 };
 
-#[allow(dead_code)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Event {
     Quit,
+    Resume,
     Error,
     Closed,
     RenderTick,
     AppTick,
     Key(KeyEvent),
     Mouse(MouseEvent),
+    Paste(String),
     Resize(u16, u16),
+    #[serde(skip)]
+    External(External),
+}
+
+/// An event yielded by a source registered with [`AppBuilder::with_source`](super::AppBuilder::with_source),
+/// downcast it back to the source's item type.
+#[derive(Clone)]
+pub struct External(Arc<dyn Any + Send + Sync>);
+
+impl External {
+    pub fn new<T: Any + Send + Sync>(event: T) -> Self {
+        Self(Arc::new(event))
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+}
+
+impl fmt::Debug for External {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("External(..)")
+    }
+}
+
+/// External sources shared between event handlers, so they keep their state across a suspend.
+pub type Sources = Arc<Mutex<SelectAll<BoxStream<'static, External>>>>;
+
+/// Messages from the host, shared between event handlers like [`Sources`].
+pub type Inbox<M> = Arc<Mutex<mpsc::UnboundedReceiver<M>>>;
+
+/// The events of a [`Replay`](super::Replay) still to come, shared between event handlers like
+/// [`Sources`] so that a suspend does not cut the session short.
+pub type Playback = Arc<Mutex<mpsc::UnboundedReceiver<Event>>>;
+
+/// What an [`App`](super::App) feeds its event handlers besides terminal input and ticks.
+pub(super) struct Feeds<M> {
+    pub recorder: Option<Recorder>,
+    pub replay: Option<Playback>,
+    pub sources: Sources,
+    pub inbox: Inbox<M>,
+    pub signals: Option<Arc<Signals>>,
+}
+
+impl<M> Default for Feeds<M> {
+    fn default() -> Self {
+        Self {
+            recorder: None,
+            replay: None,
+            sources: Sources::default(),
+            inbox: Arc::new(Mutex::new(mpsc::unbounded_channel().1)),
+            signals: None,
+        }
+    }
 }
 
 pub struct EventHandler {
-    pub task: JoinHandle<()>,
+    pub task: JoinHandle<Result<()>>,
     cancellation_token: CancellationToken,
 }
 
 impl EventHandler {
-    pub fn new(
+    /// Reads terminal input and ticks every `tick_rate` (app, render) milliseconds until stopped,
+    /// sending the actions `home` turns them into to `action_tx`.
+    pub fn new<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static>(
         tick_rate: (u64, u64),
-        home: Arc<Mutex<Base>>,
-        action_tx: mpsc::UnboundedSender<Action>,
+        home: Arc<Mutex<C>>,
+        action_tx: mpsc::UnboundedSender<Action<A>>,
     ) -> Self {
-        let (app_tick_rate, render_tick_rate) = (
-            Duration::from_millis(tick_rate.0),
-            Duration::from_millis(tick_rate.1),
-        );
+        Self::spawn(tick_rate, home, action_tx, Feeds::default())
+    }
 
+    /// Like [`EventHandler::new`], also reading everything in `feeds`.
+    pub(super) fn spawn<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static>(
+        tick_rate: (u64, u64),
+        home: Arc<Mutex<C>>,
+        action_tx: mpsc::UnboundedSender<Action<A>>,
+        feeds: Feeds<M>,
+    ) -> Self {
         let (event_tx, mut event_rx) = mpsc::unbounded_channel();
 
         let cancellation_token = CancellationToken::new();
         let _cancellation_token = cancellation_token.clone();
 
-        let task = tokio::spawn(async move {
+        let task: JoinHandle<Result<()>> = tokio::spawn(async move {
+            let Feeds {
+                recorder,
+                replay,
+                sources,
+                inbox,
+                signals,
+            } = feeds;
             let mut reader = crossterm::event::EventStream::new();
-            let (mut app_interval, mut render_interval) = (
-                tokio::time::interval(app_tick_rate),
-                tokio::time::interval(render_tick_rate),
-            );
+            let mut sources = sources.lock().await;
+            let mut inbox = inbox.lock().await;
+            let mut signals = match &signals {
+                Some(signals) => Some(signals.rx.lock().await),
+                None => None,
+            };
+            // While replaying, the log stands in for the terminal until it runs out.
+            let (mut replaying, replay) = match replay {
+                Some(replay) => (true, replay),
+                None => (false, Arc::new(Mutex::new(mpsc::unbounded_channel().1))),
+            };
+            let mut replay = replay.lock().await;
+            let mut ticks = Ticks::new(tick_rate);
 
             loop {
-                let (app_delay, render_delay) = (app_interval.tick(), render_interval.tick());
                 let crossterm_event = reader.next().fuse();
+                let signal = async {
+                    match &mut signals {
+                        Some(rx) => rx.recv().await,
+                        None => std::future::pending().await,
+                    }
+                };
                 tokio::select! {
                     _ = _cancellation_token.cancelled() => {
                         break;
                     }
-                    maybe_event = crossterm_event => {
+                    Some(signal) = signal => {
+                        event_tx.send(signal)?;
+                    },
+                    maybe_event = replay.recv(), if replaying => {
+                        match maybe_event {
+                            Some(evt) => event_tx.send(evt)?,
+                            None => replaying = false,
+                        }
+                    },
+                    Some(external) = sources.next(), if !sources.is_empty() => {
+                        event_tx.send(Event::External(external))?;
+                    },
+                    Some(message) = inbox.recv() => {
+                        let action = home.lock().await.handle_message(message);
+                        action_tx.send(action)?;
+                    },
+                    maybe_event = crossterm_event, if !replaying => {
                         match maybe_event {
                             Some(Ok(evt)) => {
-                                match evt {
-                                    CrosstermEvent::Key(key) => {
-                                        if key.kind == KeyEventKind::Press {
-                                            event_tx.send(Event::Key(key)).unwrap();
-                                        }
+                                let evt = match evt {
+                                    CrosstermEvent::Key(key) if key.kind == KeyEventKind::Press => {
+                                        Event::Key(key)
                                     },
-                                    CrosstermEvent::Resize(x, y) => {
-                                        event_tx.send(Event::Resize(x, y)).unwrap();
-                                    },
-                                    _ => {},
+                                    CrosstermEvent::Mouse(mouse) => Event::Mouse(mouse),
+                                    CrosstermEvent::Paste(s) => Event::Paste(s),
+                                    CrosstermEvent::Resize(x, y) => Event::Resize(x, y),
+                                    _ => continue,
+                                };
+                                if let Some(recorder) = &recorder {
+                                    recorder.record(&evt).context("Unable to record event")?;
                                 }
+                                event_tx.send(evt)?;
                             }
-                            Some(Err(_)) => {
-                                event_tx.send(Event::Error).unwrap();
+                            Some(Err(e)) => {
+                                return Err(e).context("Unable to read terminal input");
                             }
-                            None => {},
+                            None => bail!("Terminal input closed"),
                         }
                     },
-                    _ = app_delay => {
-                        event_tx.send(Event::AppTick).unwrap();
-                    },
-                    _ = render_delay => {
-                        event_tx.send(Event::RenderTick).unwrap();
+                    tick = ticks.next() => {
+                        event_tx.send(tick)?;
                     },
                     event = event_rx.recv() => {
                         let action = home.lock().await.handle_events(event);
-                        action_tx.send(action).unwrap();
+                        action_tx.send(action)?;
                     }
                 }
             }
+            Ok(())
         });
         Self {
             task,
@@ -0,0 +1,340 @@
+//! Where a running instance can be found by other processes: a pid file in the per-user runtime
+//! directory naming the instance whose control socket lies next to it, and the lock keeping a
+//! single instance running when asked to.
+
+use std::{
+    fs::{self, File},
+    io::{self, Seek, SeekFrom, Write},
+    os::{
+        fd::AsRawFd,
+        unix::fs::{DirBuilderExt, MetadataExt},
+    },
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+};
+
+use super::{sleep, Instant};
+
+/// How long a second launch waits for the instance holding the lock to start listening.
+const STARTUP: Duration = Duration::from_secs(1);
+
+/// `$XDG_RUNTIME_DIR/<app>`, or a directory of the user's own in the temp directory where there is
+/// no runtime directory.
+pub fn runtime_dir(app: &str) -> PathBuf {
+    dirs::runtime_dir()
+        .map(|dir| dir.join(app))
+        .unwrap_or_else(|| {
+            let user = std::env::var("USER").unwrap_or_else(|_| "default".into());
+            std::env::temp_dir().join(format!("{app}-{user}"))
+        })
+}
+
+/// Creates `dir` for the user alone, refusing one that someone else could have put in place or
+/// can write to, e.g. in a shared temp directory.
+fn create_dir(dir: &Path) -> Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("Unable to create {}", dir.display()))?;
+    let meta =
+        fs::symlink_metadata(dir).with_context(|| format!("Unable to read {}", dir.display()))?;
+    // SAFETY: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o777 != 0o700 {
+        bail!(
+            "{} must be a directory owned by the current user with mode 0700",
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+fn pid_path(dir: &Path) -> PathBuf {
+    dir.join("instance.pid")
+}
+
+fn lock_path(dir: &Path) -> PathBuf {
+    dir.join("instance.lock")
+}
+
+fn socket_path(dir: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("{pid}.sock"))
+}
+
+/// This process, registered as the instance `send` talks to until dropped.
+pub struct Instance {
+    dir: PathBuf,
+    pid: u32,
+}
+
+impl Instance {
+    /// Writes the pid file into `dir`, replacing the one of an earlier instance that is gone. While
+    /// the registered instance still answers on its socket it stays the one `send` talks to, and
+    /// `None` is returned.
+    pub fn register(dir: &Path) -> Result<Option<Self>> {
+        create_dir(dir)?;
+        let path = pid_path(dir);
+        let registered = fs::read_to_string(&path)
+            .ok()
+            .and_then(|pid| pid.trim().parse().ok());
+        if let Some(pid) = registered {
+            if std::os::unix::net::UnixStream::connect(socket_path(dir, pid)).is_ok() {
+                return Ok(None);
+            }
+        }
+        let pid = std::process::id();
+        fs::write(&path, format!("{pid}\n"))
+            .with_context(|| format!("Unable to write {}", path.display()))?;
+        Ok(Some(Self {
+            dir: dir.to_path_buf(),
+            pid,
+        }))
+    }
+
+    /// Where this instance listens for control requests.
+    pub fn socket(&self) -> PathBuf {
+        socket_path(&self.dir, self.pid)
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        // A later instance may have taken over the pid file
+        let path = pid_path(&self.dir);
+        if fs::read_to_string(&path).is_ok_and(|pid| pid.trim() == self.pid.to_string()) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Held by the only instance while running in single-instance mode. The operating system releases
+/// it when the process ends, so a lock left behind by one that died is free to be taken over. The
+/// lock file names the holder and where it listens, for later launches to hand it their arguments.
+pub struct Lock {
+    file: File,
+}
+
+impl Lock {
+    /// Takes the lock in `dir`, or returns `None` while a running instance holds it.
+    pub fn acquire(dir: &Path) -> Result<Option<Self>> {
+        create_dir(dir)?;
+        let path = lock_path(dir);
+        let file = File::options()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Unable to open {}", path.display()))?;
+        // SAFETY: the descriptor stays open for as long as `file` lives
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::WouldBlock {
+                return Ok(None);
+            }
+            return Err(e).with_context(|| format!("Unable to lock {}", path.display()));
+        }
+        let mut lock = Self { file };
+        // Whoever wrote in there before is gone
+        lock.write(None)?;
+        Ok(Some(lock))
+    }
+
+    /// Records the control socket this instance listens on, the one [`send_message`] delivers to.
+    pub fn listen_at(&mut self, socket: &Path) -> Result<()> {
+        self.write(Some(socket))
+    }
+
+    fn write(&mut self, socket: Option<&Path>) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        writeln!(self.file, "{}", std::process::id())?;
+        if let Some(socket) = socket {
+            writeln!(self.file, "{}", socket.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns command line words such as `increment 5`, `schedule-increment` or `quit` into the action
+/// notation of the keymap, e.g. `Increment(5)`. Words already in that notation pass unchanged.
+pub fn action_from_words(name: &str, args: &[String]) -> String {
+    let name: String = name
+        .split(['-', '_'])
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+        })
+        .collect();
+    match args {
+        [] => name,
+        args => format!("{name}({})", args.join(" ")),
+    }
+}
+
+/// Dispatches `action` in the instance registered in `dir` and returns its response.
+pub async fn send_action(dir: &Path, action: String) -> Result<Value> {
+    let stream = connect(dir).await?;
+    request(stream, json!({ "cmd": "action", "action": action })).await
+}
+
+/// Hands `message` to the instance holding the [`Lock`] in `dir`, giving one that is still starting
+/// up a moment to start listening.
+pub async fn send_message<M: Serialize>(dir: &Path, message: &M) -> Result<Value> {
+    let until = Instant::now() + STARTUP;
+    let stream = loop {
+        match connect_holder(dir).await {
+            Ok(stream) => break stream,
+            Err(_) if Instant::now() < until => sleep(Duration::from_millis(50)).await,
+            Err(e) => return Err(e),
+        }
+    };
+    request(stream, json!({ "cmd": "message", "message": message })).await
+}
+
+/// Connects to the socket the holder of the lock recorded with [`Lock::listen_at`].
+async fn connect_holder(dir: &Path) -> Result<UnixStream> {
+    let not_listening = || anyhow!("The running instance does not listen for messages");
+    let lock = fs::read_to_string(lock_path(dir)).map_err(|_| not_listening())?;
+    let socket = lock.lines().nth(1).ok_or_else(not_listening)?;
+    UnixStream::connect(socket)
+        .await
+        .map_err(|_| not_listening())
+}
+
+async fn connect(dir: &Path) -> Result<UnixStream> {
+    let no_instance = || anyhow!("No instance is running");
+    let pid = fs::read_to_string(pid_path(dir)).map_err(|_| no_instance())?;
+    let pid = pid.trim().parse().map_err(|_| no_instance())?;
+    UnixStream::connect(socket_path(dir, pid))
+        .await
+        .map_err(|_| no_instance())
+}
+
+/// Writes one request line and reads the response line.
+async fn request(stream: UnixStream, request: Value) -> Result<Value> {
+    let (read, mut write) = stream.into_split();
+    let mut request = serde_json::to_vec(&request)?;
+    request.push(b'\n');
+    write.write_all(&request).await?;
+    let line = BufReader::new(read)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or_else(|| anyhow!("The instance closed the connection"))?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::{
+        testing::{Count, Counter, Harness, Note},
+        Action,
+    };
+
+    #[test]
+    fn words_become_actions() {
+        let words = |s: &str| {
+            let mut words = s.split(' ').map(String::from);
+            let name = words.next().unwrap();
+            action_from_words(&name, &words.collect::<Vec<_>>())
+        };
+        assert_eq!(words("quit"), "Quit");
+        assert_eq!(words("increment 5"), "Increment(5)");
+        assert_eq!(words("schedule-increment"), "ScheduleIncrement");
+        assert_eq!(words("notify hello there"), "Notify(hello there)");
+        assert_eq!(words("Increment(5)"), "Increment(5)");
+        assert!(words("push-mode insert").parse::<Action<Count>>().is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lock_is_taken_over_once_released() {
+        let dir = std::env::temp_dir().join(format!("shadotui-lock-{}", std::process::id()));
+        let path = dir.join("instance.lock");
+        create_dir(&dir).unwrap();
+        // Left behind by an instance that died
+        fs::write(&path, "999999999\n").unwrap();
+
+        let lock = Lock::acquire(&dir).unwrap().expect("stale lock taken over");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap().trim(),
+            std::process::id().to_string()
+        );
+        assert!(Lock::acquire(&dir).unwrap().is_none());
+        assert!(send_message(&dir, &Note::Quit).await.is_err());
+
+        drop(lock);
+        assert!(Lock::acquire(&dir).unwrap().is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn forwards_to_wherever_the_lock_holder_listens() {
+        let dir = std::env::temp_dir().join(format!("shadotui-forward-{}", std::process::id()));
+        let socket =
+            std::env::temp_dir().join(format!("shadotui-elsewhere-{}", std::process::id()));
+        let mut h = Harness::new(Counter::default(), 40, 12).await;
+        let mut lock = Lock::acquire(&dir).unwrap().unwrap();
+        let control = h.control(&socket).unwrap();
+        lock.listen_at(&socket).unwrap();
+
+        let notify = Note::Notify("forwarded".into());
+        let response = send_message(&dir, &notify).await.unwrap();
+        assert_eq!(response, json!({ "ok": true }));
+        h.settle().await;
+        assert!(h.lines().await.iter().any(|l| l.contains("forwarded")));
+
+        drop((control, lock));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn runtime_dir_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("shadotui-shared-{}", std::process::id()));
+        create_dir(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        let error = create_dir(&dir).unwrap_err();
+        assert!(error.to_string().contains("mode 0700"), "{error}");
+        assert!(Instance::register(&dir).is_err());
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn sends_to_the_registered_instance() {
+        let dir = std::env::temp_dir().join(format!("shadotui-instance-{}", std::process::id()));
+        assert!(send_action(&dir, "Quit".into()).await.is_err());
+
+        let mut h = Harness::new(Counter::default(), 40, 12).await;
+        let instance = Instance::register(&dir).unwrap().unwrap();
+        let control = h.control(&instance.socket()).unwrap();
+        let response = send_action(&dir, action_from_words("increment", &["5".into()]))
+            .await
+            .unwrap();
+        assert_eq!(response, json!({ "ok": true }));
+        h.settle().await;
+        assert!(h.lines().await.iter().any(|l| l.contains("Counter: 5")));
+
+        let response = send_action(&dir, "Bogus".into()).await.unwrap();
+        assert_eq!(response["ok"], false);
+        // A second instance leaves the running one registered
+        assert!(Instance::register(&dir).unwrap().is_none());
+        assert!(send_action(&dir, "Quit".into()).await.is_ok());
+
+        drop((control, instance));
+        assert!(send_action(&dir, "Quit".into()).await.is_err());
+        fs::remove_dir(&dir).unwrap();
+    }
+}
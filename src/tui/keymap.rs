@@ -0,0 +1,702 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use toml::Spanned;
+
+use super::{Action, AppAction, Instant, Mode, ModeStack, Unbound};
+
+const DEFAULT_KEYMAP: &str = include_str!("default_keymap.toml");
+const DEFAULT_LEADER: &str = "\\";
+
+type KeymapFile = HashMap<Spanned<String>, HashMap<Spanned<String>, Spanned<String>>>;
+
+/// A node of the binding trie, holding the action bound to the keys leading to it.
+#[derive(Clone, Debug)]
+struct Node<A> {
+    action: Option<Action<A>>,
+    children: HashMap<KeyEvent, Node<A>>,
+}
+
+impl<A> Default for Node<A> {
+    fn default() -> Self {
+        Self {
+            action: None,
+            children: HashMap::new(),
+        }
+    }
+}
+
+/// Key bindings for every [`Mode`] to the runtime's actions and the application's own of type
+/// `A`, built from the defaults with the user's keymap file on top.
+#[derive(Clone, Debug)]
+pub struct KeyMap<A> {
+    modes: HashMap<Mode, Node<A>>,
+    unbound: HashMap<Mode, Unbound>,
+    leader: Vec<KeyEvent>,
+    timeout: Duration,
+}
+
+impl<A> Default for KeyMap<A> {
+    fn default() -> Self {
+        Self {
+            modes: HashMap::new(),
+            unbound: Mode::ALL.into_iter().map(|m| (m, m.unbound())).collect(),
+            leader: parse_keys(DEFAULT_LEADER, &[]).unwrap_or_default(),
+            timeout: Duration::from_millis(1000),
+        }
+    }
+}
+
+impl<A: AppAction> KeyMap<A> {
+    /// Loads the built-in bindings, then the application's `defaults` in the same format, and
+    /// merges `path` over them. Without a `path` the keymap in the config directory of `app` is
+    /// used if it exists.
+    pub fn load(app: &str, defaults: &str, path: Option<PathBuf>) -> Result<Self> {
+        Self::load_with_modes(app, defaults, path, [])
+    }
+
+    /// Like [`KeyMap::load`], registering the application's own modes first so that the keymap
+    /// file can bind keys in them. Built-in modes listed here get their unbound keys handled
+    /// differently.
+    pub fn load_with_modes(
+        app: &str,
+        defaults: &str,
+        path: Option<PathBuf>,
+        modes: impl IntoIterator<Item = (Mode, Unbound)>,
+    ) -> Result<Self> {
+        let mut keymap = Self::default();
+        keymap.unbound.extend(modes);
+        keymap.merge(DEFAULT_KEYMAP, "<default keymap>")?;
+        keymap.merge(defaults, "<application keymap>")?;
+
+        let path = match path {
+            Some(path) => path,
+            None => match config_path(app) {
+                Some(path) if path.exists() => path,
+                _ => return Ok(keymap),
+            },
+        };
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read keymap {}", path.display()))?;
+        keymap.merge(&source, &path.display().to_string())?;
+        Ok(keymap)
+    }
+
+    /// The built-in bindings and the application's `defaults` alone, whatever is in the config
+    /// directory, e.g. for tests.
+    pub fn defaults(defaults: &str) -> Result<Self> {
+        let mut keymap = Self::default();
+        keymap.merge(DEFAULT_KEYMAP, "<default keymap>")?;
+        keymap.merge(defaults, "<application keymap>")?;
+        Ok(keymap)
+    }
+
+    /// How long a partially typed sequence waits for its next key.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn node(&self, mode: Mode, keys: &[KeyEvent]) -> Option<&Node<A>> {
+        keys.iter()
+            .try_fold(self.modes.get(&mode)?, |node, key| node.children.get(key))
+    }
+
+    /// Looks up a built-in or registered mode by its name.
+    fn mode(&self, name: &str) -> Result<Mode> {
+        self.unbound
+            .keys()
+            .copied()
+            .find(|m| m.name() == name)
+            .ok_or_else(|| anyhow!("unknown mode `{name}`"))
+    }
+
+    /// Parses an action, resolving the mode of `PushMode` among the registered ones.
+    fn action(&self, s: &str) -> Result<Action<A>> {
+        match s
+            .strip_prefix("PushMode(")
+            .and_then(|s| s.strip_suffix(')'))
+        {
+            Some(name) => Ok(Action::PushMode(self.mode(name.trim())?)),
+            None => s.parse(),
+        }
+    }
+
+    /// Parses `source` and overrides existing bindings with its entries. Every unknown mode, key
+    /// or action is reported together, prefixed with `name` and its line number.
+    fn merge(&mut self, source: &str, name: &str) -> Result<()> {
+        let mut file: KeymapFile = toml::from_str(source).map_err(|e| anyhow!("{name}: {e}"))?;
+
+        let line = |span: std::ops::Range<usize>| source[..span.start].matches('\n').count() + 1;
+        let mut errors = Vec::new();
+        if let Some(settings) = file.remove("settings") {
+            for (key, value) in settings {
+                match key.get_ref().as_str() {
+                    "leader" => match parse_keys(value.get_ref(), &[]) {
+                        Ok(leader) => self.leader = leader,
+                        Err(e) => errors.push((line(value.span()), e)),
+                    },
+                    setting => {
+                        errors.push((line(key.span()), anyhow!("unknown setting `{setting}`")))
+                    }
+                }
+            }
+        }
+        for (mode, bindings) in file {
+            let mode = match self.mode(mode.get_ref()) {
+                Ok(m) => m,
+                Err(e) => {
+                    errors.push((line(mode.span()), e));
+                    continue;
+                }
+            };
+            for (keys, action) in bindings {
+                let parsed = parse_keys(keys.get_ref(), &self.leader);
+                match (parsed, self.action(action.get_ref())) {
+                    (Ok(k), Ok(a)) => {
+                        let root = self.modes.entry(mode).or_default();
+                        k.into_iter()
+                            .fold(root, |node, key| node.children.entry(key).or_default())
+                            .action = Some(a);
+                    }
+                    (k, a) => {
+                        if let Err(e) = k {
+                            errors.push((line(keys.span()), e));
+                        }
+                        if let Err(e) = a {
+                            errors.push((line(action.span()), e));
+                        }
+                    }
+                }
+            }
+        }
+        if !errors.is_empty() {
+            errors.sort_by_key(|(line, _)| *line);
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|(line, e)| format!("{name}:{line}: {e:#}"))
+                .collect();
+            bail!("Invalid keymap:\n  {}", errors.join("\n  "));
+        }
+        Ok(())
+    }
+}
+
+/// What a key press resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step<A> {
+    /// A complete binding, with any count prefix already applied.
+    Action(Action<A>),
+    /// A key that starts no binding, left for the component to handle in the mode that swallowed
+    /// it.
+    Key(Mode, KeyEvent),
+}
+
+/// Buffers key presses until they resolve to a binding of the [`KeyMap`], a count prefix like
+/// `5j` included.
+#[derive(Clone, Debug)]
+pub struct KeyMatcher<A> {
+    keymap: KeyMap<A>,
+    pending: Vec<KeyEvent>,
+    count: Option<usize>,
+    last: Option<Instant>,
+}
+
+impl<A: AppAction> Default for KeyMatcher<A> {
+    fn default() -> Self {
+        Self::new(KeyMap::default())
+    }
+}
+
+impl<A: AppAction> KeyMatcher<A> {
+    pub fn new(keymap: KeyMap<A>) -> Self {
+        Self {
+            keymap,
+            pending: Vec::new(),
+            count: None,
+            last: None,
+        }
+    }
+
+    /// Feeds a key press, returning nothing while the keys typed so far may still grow into a
+    /// longer binding. Bindings of the active mode come first, keys it passes through fall to
+    /// the modes below.
+    pub fn feed(&mut self, modes: &ModeStack, key: KeyEvent) -> Vec<Step<A>> {
+        let mode = modes.current();
+        let key = normalize(KeyEvent::new(key.code, key.modifiers));
+        self.last = Some(Instant::now());
+        if let (KeyCode::Char(c @ '0'..='9'), KeyModifiers::NONE) = (key.code, key.modifiers) {
+            // Digits ending up in a mode that keeps them, such as insert, are typed rather than
+            // counted
+            let counts = self.pending.is_empty()
+                && (c != '0' || self.count.is_some())
+                && self.keymap.node(mode, &[key]).is_none()
+                && matches!(
+                    self.pass_through(modes, key, 1),
+                    Step::Key(m, _) if self.keymap.unbound.get(&m) == Some(&Unbound::Count)
+                );
+            if counts {
+                let digit = c as usize - '0' as usize;
+                let count = self.count.unwrap_or(0).saturating_mul(10);
+                self.count = Some(count.saturating_add(digit));
+                return Vec::new();
+            }
+        }
+        self.pending.push(key);
+        self.resolve(modes, false)
+    }
+
+    /// Called on every tick, flushes the pending keys once they have waited longer than the
+    /// timeout. The longest bound prefix still fires.
+    pub fn timeout(&mut self, modes: &ModeStack) -> Vec<Step<A>> {
+        match self.last {
+            Some(last) if last.elapsed() >= self.keymap.timeout => {
+                self.last = None;
+                let steps = self.resolve(modes, true);
+                self.count = None;
+                steps
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The count and keys typed so far, e.g. `5g`.
+    pub fn pending(&self) -> String {
+        let count = self.count.map(|c| c.to_string()).unwrap_or_default();
+        let keys: String = self.pending.iter().map(|k| key_to_string(*k)).collect();
+        count + &keys
+    }
+
+    fn resolve(&mut self, modes: &ModeStack, flush: bool) -> Vec<Step<A>> {
+        let mode = modes.current();
+        let mut steps = Vec::new();
+        while !self.pending.is_empty() {
+            if let Some(node) = self.keymap.node(mode, &self.pending) {
+                if !flush && !node.children.is_empty() {
+                    break;
+                }
+            }
+            let bound = (1..=self.pending.len()).rev().find_map(|len| {
+                let action = self
+                    .keymap
+                    .node(mode, &self.pending[..len])?
+                    .action
+                    .clone()?;
+                Some((len, action))
+            });
+            match bound {
+                Some((len, action)) => {
+                    self.pending.drain(..len);
+                    let count = self.count.take().unwrap_or(1);
+                    steps.push(Step::Action(action.with_count(count)));
+                }
+                None => {
+                    let count = self.count.take().unwrap_or(1);
+                    let key = self.pending.remove(0);
+                    steps.push(self.pass_through(modes, key, count));
+                }
+            }
+        }
+        steps
+    }
+
+    /// Hands a key unbound in the active mode down the stack for as long as the modes pass it on,
+    /// until one binds it on its own or keeps it.
+    fn pass_through(&self, modes: &ModeStack, key: KeyEvent, count: usize) -> Step<A> {
+        let mut modes = modes.iter().peekable();
+        while let Some(mode) = modes.next() {
+            let passes = self
+                .keymap
+                .unbound
+                .get(&mode)
+                .is_some_and(|u| u.passes(&key));
+            let Some(&below) = modes.peek().filter(|_| passes) else {
+                return Step::Key(mode, key);
+            };
+            if let Some(action) = self
+                .keymap
+                .node(below, &[key])
+                .and_then(|n| n.action.clone())
+            {
+                return Step::Action(action.with_count(count));
+            }
+        }
+        Step::Key(Mode::Normal, key)
+    }
+}
+
+/// Location of the user's keymap, `$XDG_CONFIG_HOME/<app>/keymap.toml` on Linux.
+pub fn config_path(app: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(app).join("keymap.toml"))
+}
+
+/// Parses a key sequence such as `g`, `gg`, `<C-w>j` or `<leader>x`. Whitespace between keys is
+/// ignored, use `<Space>` for the space bar.
+pub fn parse_keys(s: &str, leader: &[KeyEvent]) -> Result<Vec<KeyEvent>> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(vec![KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)]);
+    }
+    let mut keys = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = match rest.find('>') {
+            Some(end) if c == '<' => end + 1,
+            _ => c.len_utf8(),
+        };
+        let (token, tail) = rest.split_at(len);
+        if token.eq_ignore_ascii_case("<leader>") {
+            keys.extend_from_slice(leader);
+        } else {
+            keys.push(parse_key(token)?);
+        }
+        rest = tail.trim_start();
+    }
+    if keys.is_empty() {
+        bail!("empty key sequence");
+    }
+    Ok(keys)
+}
+
+/// Parses a single key such as `g`, `<Esc>`, `<C-d>` or `<A-S-Tab>`.
+pub fn parse_key(s: &str) -> Result<KeyEvent> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    }
+    let Some(mut inner) = s.strip_prefix('<').and_then(|s| s.strip_suffix('>')) else {
+        bail!("unknown key `{s}`, expected a single character or `<...>`");
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    while let Some((modifier, rest)) = inner.split_once('-').filter(|(_, r)| !r.is_empty()) {
+        modifiers |= match modifier {
+            "C" | "c" => KeyModifiers::CONTROL,
+            "A" | "a" | "M" | "m" => KeyModifiers::ALT,
+            "S" | "s" => KeyModifiers::SHIFT,
+            _ => bail!("unknown modifier `{modifier}` in key `{s}`"),
+        };
+        inner = rest;
+    }
+
+    let mut chars = inner.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match inner.to_ascii_lowercase().as_str() {
+            "esc" => KeyCode::Esc,
+            "enter" | "cr" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "bs" | "backspace" => KeyCode::Backspace,
+            "del" | "delete" => KeyCode::Delete,
+            "ins" | "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            "lt" => KeyCode::Char('<'),
+            "gt" => KeyCode::Char('>'),
+            name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                _ => bail!("unknown key `{s}`"),
+            },
+        },
+    };
+    Ok(normalize(KeyEvent::new(code, modifiers)))
+}
+
+/// Folds SHIFT into the character it produces, the way terminals report `G` as `Char('G')` with
+/// SHIFT held, so that `G`, `<S-g>` and the key press all compare equal.
+fn normalize(key: KeyEvent) -> KeyEvent {
+    match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::SHIFT) => {
+            let c = c.to_uppercase().next().unwrap_or(c);
+            KeyEvent::new(KeyCode::Char(c), key.modifiers - KeyModifiers::SHIFT)
+        }
+        _ => key,
+    }
+}
+
+/// Formats a key the way [`parse_key`] reads it.
+pub fn key_to_string(key: KeyEvent) -> String {
+    let name = match key.code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char('<') => "lt".to_string(),
+        KeyCode::Char(c) if key.modifiers.difference(KeyModifiers::SHIFT).is_empty() => {
+            return c.to_string();
+        }
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::BackTab => "BackTab".to_string(),
+        KeyCode::Backspace => "BS".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::Insert => "Ins".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        code => format!("{code:?}"),
+    };
+    let mut prefix = String::new();
+    for (modifier, p) in [
+        (KeyModifiers::CONTROL, "C-"),
+        (KeyModifiers::ALT, "A-"),
+        (KeyModifiers::SHIFT, "S-"),
+    ] {
+        if key.modifiers.contains(modifier) {
+            prefix.push_str(p);
+        }
+    }
+    format!("<{prefix}{name}>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::testing::Count;
+
+    fn keymap(source: &str) -> KeyMap<Count> {
+        let mut keymap = KeyMap::<Count>::default();
+        keymap.merge(source, "<test>").unwrap();
+        keymap
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn shifted_characters_match_uppercase_bindings() {
+        let mut keys = KeyMatcher::new(keymap("[normal]\nG = \"Quit\"\n\"<S-x>\" = \"Suspend\""));
+        let modes = ModeStack::default();
+        let shifted = |c| key(KeyCode::Char(c), KeyModifiers::SHIFT);
+
+        assert_eq!(
+            keys.feed(&modes, shifted('G')),
+            [Step::Action(Action::Quit)]
+        );
+        assert_eq!(
+            keys.feed(&modes, shifted('X')),
+            [Step::Action(Action::Suspend)]
+        );
+        assert_eq!(parse_key("<S-g>").unwrap(), parse_key("G").unwrap());
+        assert_eq!(
+            parse_key("<C-S-a>").unwrap(),
+            key(KeyCode::Char('A'), KeyModifiers::CONTROL)
+        );
+    }
+
+    #[test]
+    fn keys_round_trip_through_their_names() {
+        for name in [
+            "g",
+            "G",
+            "<Esc>",
+            "<C-d>",
+            "<A-S-Tab>",
+            "<F5>",
+            "<Space>",
+            "<lt>",
+            "<C-A>",
+        ] {
+            let key = parse_key(name).unwrap();
+            assert_eq!(parse_key(&key_to_string(key)).unwrap(), key, "{name}");
+        }
+        assert_eq!(key_to_string(parse_key("<c-x>").unwrap()), "<C-x>");
+        assert_eq!(parse_key("<Enter>").unwrap(), parse_key("<CR>").unwrap());
+
+        let leader = parse_keys("<Space>", &[]).unwrap();
+        let keys = parse_keys("<leader>g <C-w>j", &leader).unwrap();
+        let names: Vec<_> = keys.into_iter().map(key_to_string).collect();
+        assert_eq!(names, ["<Space>", "g", "<C-w>", "j"]);
+        assert_eq!(
+            parse_keys(" ", &[]).unwrap(),
+            [parse_key("<Space>").unwrap()]
+        );
+
+        assert!(parse_key("<Nope>").is_err());
+        assert!(parse_key("<X-a>").is_err());
+        assert!(parse_keys("", &[]).is_err());
+    }
+
+    #[test]
+    fn merge_reports_every_error_with_its_line() {
+        let source =
+            "[normal]\n\"<Bogus>\" = \"Quit\"\nx = \"Nothing\"\n\n[nowhere]\ny = \"Quit\"\n";
+        let error = KeyMap::<Count>::default()
+            .merge(source, "keymap.toml")
+            .unwrap_err();
+        let error = error.to_string();
+        assert!(
+            error.contains("keymap.toml:2: unknown key `<Bogus>`"),
+            "{error}"
+        );
+        assert!(
+            error.contains("keymap.toml:3: unknown action `Nothing`"),
+            "{error}"
+        );
+        assert!(
+            error.contains("keymap.toml:5: unknown mode `nowhere`"),
+            "{error}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sequences_resolve_to_the_longest_bound_prefix() {
+        let source =
+            "[normal]\ng = \"Increment\"\ngg = \"Notify(two)\"\nggg = \"Quit\"\nx = \"Suspend\"";
+        let keymap = keymap(source).with_timeout(Duration::from_millis(500));
+        let mut keys = KeyMatcher::new(keymap);
+        let modes = ModeStack::default();
+        let char = |c| key(KeyCode::Char(c), KeyModifiers::NONE);
+
+        assert!(keys.feed(&modes, char('g')).is_empty());
+        assert!(keys.feed(&modes, char('g')).is_empty());
+        assert_eq!(keys.pending(), "gg");
+        assert_eq!(keys.feed(&modes, char('g')), [Step::Action(Action::Quit)]);
+
+        // A key that continues no binding flushes the longest prefix before it
+        keys.feed(&modes, char('g'));
+        keys.feed(&modes, char('g'));
+        assert_eq!(
+            keys.feed(&modes, char('x')),
+            [
+                Step::Action(Count::Notify("two".into()).into()),
+                Step::Action(Action::Suspend)
+            ]
+        );
+
+        keys.feed(&modes, char('g'));
+        tokio::time::advance(Duration::from_millis(499)).await;
+        assert!(keys.timeout(&modes).is_empty());
+        tokio::time::advance(Duration::from_millis(1)).await;
+        assert_eq!(
+            keys.timeout(&modes),
+            [Step::Action(Action::App(Count::Increment(1)))]
+        );
+        assert_eq!(keys.pending(), "");
+    }
+
+    #[test]
+    fn counts_multiply_the_next_action() {
+        let mut keys = KeyMatcher::new(keymap("[normal]\nj = \"Increment\"\n1 = \"Quit\""));
+        let modes = ModeStack::default();
+        let char = |c| key(KeyCode::Char(c), KeyModifiers::NONE);
+
+        assert!(keys.feed(&modes, char('5')).is_empty());
+        assert_eq!(keys.pending(), "5");
+        assert_eq!(
+            keys.feed(&modes, char('j')),
+            [Step::Action(Action::App(Count::Increment(5)))]
+        );
+        // A leading 0 is a key of its own, later ones are part of the count
+        assert_eq!(
+            keys.feed(&modes, char('0')),
+            [Step::Key(Mode::Normal, char('0'))]
+        );
+        keys.feed(&modes, char('2'));
+        keys.feed(&modes, char('0'));
+        assert_eq!(
+            keys.feed(&modes, char('j')),
+            [Step::Action(Action::App(Count::Increment(20)))]
+        );
+        // Bound digits are bindings rather than counts
+        assert_eq!(keys.feed(&modes, char('1')), [Step::Action(Action::Quit)]);
+
+        let mut modes = ModeStack::default();
+        modes.push(Mode::Processing);
+        keys.feed(&modes, char('3'));
+        assert_eq!(
+            keys.feed(&modes, char('j')),
+            [Step::Action(Action::App(Count::Increment(3)))]
+        );
+        // Processing entered from insert passes digits on to the input
+        let mut modes = ModeStack::default();
+        modes.push(Mode::Insert);
+        modes.push(Mode::Processing);
+        assert_eq!(
+            keys.feed(&modes, char('3')),
+            [Step::Key(Mode::Insert, char('3'))]
+        );
+    }
+
+    #[test]
+    fn unbound_keys_pass_through_to_the_mode_below() {
+        let source = "[normal]\nj = \"Increment\"\nq = \"Quit\"\n[insert]\n\"<Esc>\" = \"EnterNormal\"\n[processing]\n\"<Esc>\" = \"ExitProcessing\"";
+        let sticky = Mode::Custom("sticky");
+        let mut keymap = KeyMap::<Count>::default();
+        keymap.unbound.insert(
+            sticky,
+            Unbound::PassOnly(vec![key(KeyCode::Char('q'), KeyModifiers::NONE)]),
+        );
+        keymap.merge(source, "<test>").unwrap();
+        let mut keys = KeyMatcher::new(keymap);
+        let char = |c| key(KeyCode::Char(c), KeyModifiers::NONE);
+
+        let mut modes = ModeStack::default();
+        modes.push(Mode::Processing);
+        assert_eq!(
+            keys.feed(&modes, char('j')),
+            [Step::Action(Action::App(Count::Increment(1)))]
+        );
+        assert_eq!(
+            keys.feed(&modes, key(KeyCode::Esc, KeyModifiers::NONE)),
+            [Step::Action(Action::ExitProcessing)]
+        );
+        assert_eq!(
+            keys.feed(&modes, char('z')),
+            [Step::Key(Mode::Normal, char('z'))]
+        );
+
+        modes.push(Mode::Insert);
+        assert_eq!(
+            keys.feed(&modes, char('j')),
+            [Step::Key(Mode::Insert, char('j'))]
+        );
+
+        let mut modes = ModeStack::default();
+        modes.push(sticky);
+        assert_eq!(keys.feed(&modes, char('q')), [Step::Action(Action::Quit)]);
+        assert_eq!(keys.feed(&modes, char('j')), [Step::Key(sticky, char('j'))]);
+    }
+
+    #[test]
+    fn modes_keeping_unbound_keys_receive_digits() {
+        let search = Mode::Custom("search");
+        let counted = Mode::Custom("counted");
+        let mut keymap = KeyMap::<Count>::default();
+        keymap.unbound.insert(search, Unbound::Swallow);
+        keymap.unbound.insert(counted, Unbound::Count);
+        let source = "[search]\n\"<Esc>\" = \"PopMode\"\n[counted]\nj = \"Increment\"";
+        keymap.merge(source, "<test>").unwrap();
+        let mut keys = KeyMatcher::new(keymap);
+        let char = |c| key(KeyCode::Char(c), KeyModifiers::NONE);
+
+        let mut modes = ModeStack::default();
+        modes.push(search);
+        assert_eq!(keys.feed(&modes, char('4')), [Step::Key(search, char('4'))]);
+        assert_eq!(keys.feed(&modes, char('2')), [Step::Key(search, char('2'))]);
+        assert_eq!(keys.pending(), "");
+
+        let mut modes = ModeStack::default();
+        modes.push(counted);
+        assert!(keys.feed(&modes, char('4')).is_empty());
+        assert_eq!(
+            keys.feed(&modes, char('j')),
+            [Step::Action(Action::App(Count::Increment(4)))]
+        );
+    }
+}
@@ -1,7 +1,57 @@
-#[allow(dead_code)]
-#[derive(Clone, PartialEq, Eq)]
-pub enum Message {
-    None,
-    Quit,
-    HelloWorld(String),
+use std::{
+    fmt::{self, Debug},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::oneshot;
+
+/// What the runtime needs from the messages an application exchanges with its host, implemented
+/// for every type with a [`Quit`] variant that can be sent between tasks.
+pub trait AppMessage: Quit + Clone + Debug + Send + 'static {}
+
+impl<M: Quit + Clone + Debug + Send + 'static> AppMessage for M {}
+
+/// The variant sent to the host once the app quits. The host sending it quits the app.
+pub trait Quit {
+    fn quit() -> Self;
+    fn is_quit(&self) -> bool;
+}
+
+/// The answering end of a request, carried by a message to the host. Clones share it and only the
+/// first reply is delivered.
+pub struct Reply<T>(Arc<Mutex<Option<oneshot::Sender<T>>>>);
+
+impl<T> Reply<T> {
+    /// A reply to put into a message, and the receiver its answer arrives on.
+    pub fn new() -> (Self, oneshot::Receiver<T>) {
+        let (tx, rx) = oneshot::channel();
+        (Self(Arc::new(Mutex::new(Some(tx)))), rx)
+    }
+
+    /// Answers the request, returning `false` if it was answered before or nobody waits anymore.
+    pub fn send(&self, value: T) -> bool {
+        let tx = self.0.lock().unwrap_or_else(|e| e.into_inner()).take();
+        tx.is_some_and(|tx| tx.send(value).is_ok())
+    }
+}
+
+impl<T> Clone for Reply<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Replies are equal when they answer the same request.
+impl<T> PartialEq for Reply<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> Eq for Reply<T> {}
+
+impl<T> Debug for Reply<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Reply(..)")
+    }
 }
@@ -1,15 +1,38 @@
 mod action;
 mod app;
+mod clock;
 mod components;
+mod control;
+mod error;
 mod event;
+mod instance;
+mod keymap;
 mod message;
+mod mode;
+mod record;
+mod signal;
 mod terminal;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod timer;
 mod utils;
 
-use action::Action;
-use event::{Event, EventHandler};
-use terminal::{Frame, TerminalHandler, Tui};
+use clock::{interval_at, sleep, sleep_until, Instant, Ticks};
+use control::{buffer_lines, Control, ParseMessage};
+use event::{Feeds, Inbox, Playback, Sources};
 
-pub use app::App;
-pub use message::Message;
+pub use action::{split_action, Action, AppAction};
+pub use app::{App, AppBuilder, Exit};
+pub use components::{relative_mouse, Component, JobContext, JobId, JobOutcome, Jobs, Placed};
+pub use control::ScreenDump;
+pub use error::{Task, TaskError};
+pub use event::{Event, EventHandler, External};
+pub use instance::{action_from_words, runtime_dir, send_action, send_message, Instance, Lock};
+pub use keymap::{KeyMap, KeyMatcher, Step};
+pub use message::{AppMessage, Quit, Reply};
+pub use mode::{Mode, ModeStack, Unbound};
+pub use record::{Pacing, Recorder, Replay};
+pub use signal::Signals;
+pub use terminal::{Backend, Frame, Output, Screen, Target, TerminalHandler, Tui};
+pub use timer::Timers;
 pub use utils::*;
@@ -0,0 +1,153 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, Error};
+use crossterm::event::KeyEvent;
+use serde::{Serialize, Serializer};
+
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Processing,
+    /// A mode defined by the application, registered with [`KeyMap::load_with_modes`] so that the
+    /// keymap can bind keys in it.
+    ///
+    /// [`KeyMap::load_with_modes`]: super::KeyMap::load_with_modes
+    Custom(&'static str),
+}
+
+impl Mode {
+    pub const ALL: [Mode; 3] = [Mode::Normal, Mode::Insert, Mode::Processing];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Normal => "normal",
+            Mode::Insert => "insert",
+            Mode::Processing => "processing",
+            Mode::Custom(name) => name,
+        }
+    }
+
+    /// What the built-in modes do with keys they have no binding for. Normal counts digits,
+    /// processing only adds a few bindings on top of the mode it was entered from.
+    pub fn unbound(&self) -> Unbound {
+        match self {
+            Mode::Normal => Unbound::Count,
+            Mode::Processing => Unbound::PassThrough,
+            _ => Unbound::Swallow,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Serialized by its name, the way the keymap refers to it.
+impl Serialize for Mode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl FromStr for Mode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mode::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| anyhow!("unknown mode `{s}`"))
+    }
+}
+
+/// What a mode does with keys it has no binding for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Unbound {
+    /// Keeps them for the component, e.g. as text typed into the input.
+    Swallow,
+    /// Takes digits as a count prefix for the next binding, like `5j`, and keeps the other keys
+    /// for the component.
+    Count,
+    /// Hands them to the mode below it on the stack.
+    PassThrough,
+    /// Hands only these keys down and keeps the rest.
+    PassOnly(Vec<KeyEvent>),
+}
+
+impl Unbound {
+    pub fn passes(&self, key: &KeyEvent) -> bool {
+        match self {
+            Unbound::Swallow | Unbound::Count => false,
+            Unbound::PassThrough => true,
+            Unbound::PassOnly(keys) => keys.contains(key),
+        }
+    }
+}
+
+/// The modes entered so far, the last one being the active mode. [`Mode::Normal`] always stays at
+/// the bottom and every mode is on the stack at most once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModeStack(Vec<Mode>);
+
+impl Default for ModeStack {
+    fn default() -> Self {
+        Self(vec![Mode::Normal])
+    }
+}
+
+impl ModeStack {
+    pub fn current(&self) -> Mode {
+        self.0.last().copied().unwrap_or_default()
+    }
+
+    pub fn contains(&self, mode: Mode) -> bool {
+        self.0.contains(&mode)
+    }
+
+    /// Enters `mode`, unless it is already on the stack. Returns whether the stack changed.
+    pub fn push(&mut self, mode: Mode) -> bool {
+        if self.contains(mode) {
+            return false;
+        }
+        self.0.push(mode);
+        true
+    }
+
+    /// Returns to the previous mode, leaving the bottom one in place.
+    pub fn pop(&mut self) -> Option<Mode> {
+        if self.0.len() > 1 {
+            self.0.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Removes `mode` wherever it is on the stack, so that leaving a mode below the active one
+    /// keeps the user where they are. Returns whether it was there.
+    pub fn leave(&mut self, mode: Mode) -> bool {
+        match self.0.iter().skip(1).position(|m| *m == mode) {
+            Some(i) => {
+                self.0.remove(i + 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The modes from the active one down to the bottom.
+    pub fn iter(&self) -> impl Iterator<Item = Mode> + '_ {
+        self.0.iter().rev().copied()
+    }
+}
+
+/// Lists the modes from the bottom up, e.g. `normal > processing > insert`.
+impl fmt::Display for ModeStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.0.iter().map(Mode::name).collect();
+        f.write_str(&names.join(" > "))
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use super::{sleep_until, Event, Instant};
+
+/// How fast a [`Replay`] feeds its events back.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Pacing {
+    /// Keep the delays between events as they were recorded.
+    #[default]
+    Realtime,
+    /// Send every event as soon as the previous one was handled.
+    Fast,
+}
+
+/// A line of the event log, `at` being milliseconds since recording started.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    at: u64,
+    event: Event,
+}
+
+/// Appends every input event to a newline delimited JSON log. Clones share the same file so the
+/// log survives the event handler being recreated on resume.
+#[derive(Clone)]
+pub struct Recorder {
+    start: Instant,
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("Unable to create event log {}", path.display()))?;
+        Ok(Self {
+            start: Instant::now(),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Writes and flushes `event` right away, so that the log is complete even after a crash.
+    pub fn record(&self, event: &Event) -> Result<()> {
+        let entry = Entry {
+            at: self.start.elapsed().as_millis() as u64,
+            event: event.clone(),
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| anyhow!("Event log poisoned"))?;
+        file.write_all(line.as_bytes())?;
+        Ok(file.flush()?)
+    }
+}
+
+/// Events read back from a [`Recorder`] log.
+pub struct Replay {
+    entries: Vec<Entry>,
+    pacing: Pacing,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>, pacing: Pacing) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Unable to open event log {}", path.display()))?;
+        let entries = BufReader::new(file)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !matches!(line, Ok(l) if l.trim().is_empty()))
+            .map(|(i, line)| {
+                serde_json::from_str(&line?)
+                    .with_context(|| format!("{}:{}: invalid event", path.display(), i + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { entries, pacing })
+    }
+
+    /// Spawns a task feeding the events into the returned channel, which closes once the log is
+    /// exhausted.
+    pub fn spawn(self) -> mpsc::UnboundedReceiver<Event> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let start = Instant::now();
+            for entry in self.entries {
+                if self.pacing == Pacing::Realtime {
+                    sleep_until(start + Duration::from_millis(entry.at)).await;
+                } else {
+                    tokio::task::yield_now().await;
+                }
+                if tx.send(entry.event).is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::{
+        testing::{Counter, Harness},
+        App, Component,
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn replay_reproduces_the_recorded_session() {
+        let path =
+            std::env::temp_dir().join(format!("shadotui-record-{}.jsonl", std::process::id()));
+        let app = App::builder(Counter::default())
+            .with_recorder(Recorder::create(&path).unwrap())
+            .build();
+        let mut recorded = Harness::with_app(app, 60, 12).await;
+        recorded.keys("2j").await;
+        recorded.advance(Duration::from_secs(6)).await;
+        recorded.keys("/abc").await;
+        let expected = recorded.root().snapshot();
+        assert_eq!(expected["counter"], 2);
+
+        let mut replayed = Harness::new(Counter::default(), 60, 12).await;
+        replayed
+            .replay(Replay::load(&path, Pacing::Realtime).unwrap())
+            .await;
+        let actual = replayed.root().snapshot();
+        for field in ["counter", "modes", "input"] {
+            assert_eq!(actual[field], expected[field], "{field}");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use anyhow::Result;
+use signal_hook::{
+    consts::signal::{SIGCONT, SIGHUP, SIGINT, SIGTERM},
+    iterator::{Handle, Signals as SignalIterator},
+};
+use tokio::sync::{mpsc, Mutex};
+
+use super::Event;
+
+/// Signals the app reacts to, forwarded from a background thread. Registered once per
+/// [`App::run`](super::App::run) and shared between event handlers so that none are missed while
+/// suspended.
+pub struct Signals {
+    pub rx: Mutex<mpsc::UnboundedReceiver<Event>>,
+    handle: Handle,
+    hung_up: Arc<AtomicBool>,
+}
+
+impl Signals {
+    pub fn new() -> Result<Arc<Self>> {
+        let mut signals = SignalIterator::new([SIGTERM, SIGHUP, SIGINT, SIGCONT])?;
+        let handle = signals.handle();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let hung_up = Arc::new(AtomicBool::new(false));
+        let hangup = hung_up.clone();
+        std::thread::spawn(move || {
+            for signal in signals.forever() {
+                let event = match signal {
+                    SIGCONT => Event::Resume,
+                    SIGHUP => {
+                        hangup.store(true, Ordering::Relaxed);
+                        Event::Quit
+                    }
+                    _ => Event::Quit,
+                };
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Arc::new(Self {
+            rx: Mutex::new(rx),
+            handle,
+            hung_up,
+        }))
+    }
+
+    /// Whether the terminal hung up, taking with it any chance to restore it.
+    pub fn hung_up(&self) -> bool {
+        self.hung_up.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Signals {
+    fn drop(&mut self) {
+        self.handle.close();
+    }
+}
@@ -1,104 +1,354 @@
-use std::sync::Arc;
+use std::{
+    fs::File,
+    io::{self, Write},
+    sync::Arc,
+};
 
 use anyhow::{anyhow, Context, Result};
 use crossterm::{
     cursor,
-    event::{DisableMouseCapture, EnableMouseCapture},
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode}, execute,
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute,
+    style::Print,
+    terminal::{disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+#[cfg(any(test, feature = "testing"))]
+use ratatui::backend::TestBackend;
+use ratatui::{
+    backend::{ClearType, CrosstermBackend},
+    buffer::Cell,
+    layout::Rect,
+    Terminal, TerminalOptions, Viewport,
 };
-use ratatui::{backend::CrosstermBackend as Backend, Terminal};
 use tokio::{
     sync::{mpsc, Mutex},
     task::JoinHandle,
 };
 
-use super::components::{Base, Component};
+use super::{buffer_lines, components::Component, AppAction, AppMessage, ScreenDump};
+
+pub type Frame<'a> = ratatui::Frame<'a, Backend>;
+
+/// The stream the terminal is drawn to.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Target {
+    #[default]
+    Stderr,
+    Stdout,
+    /// The controlling terminal, which stays drawable with both stdout and stderr redirected.
+    Tty,
+}
+
+impl Target {
+    pub fn open(self) -> Result<Output> {
+        Ok(match self {
+            Target::Stderr => Output::Stderr(io::stderr()),
+            Target::Stdout => Output::Stdout(io::stdout()),
+            Target::Tty => Output::Tty(
+                File::options()
+                    .write(true)
+                    .open("/dev/tty")
+                    .context("Unable to open /dev/tty")?,
+            ),
+        })
+    }
+}
+
+/// An opened [`Target`].
+pub enum Output {
+    Stderr(io::Stderr),
+    Stdout(io::Stdout),
+    Tty(File),
+    /// Fails every write, like a terminal that went away.
+    #[cfg(test)]
+    Broken,
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stderr(w) => w.write(buf),
+            Output::Stdout(w) => w.write(buf),
+            Output::Tty(w) => w.write(buf),
+            #[cfg(test)]
+            Output::Broken => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stderr(w) => w.flush(),
+            Output::Stdout(w) => w.flush(),
+            Output::Tty(w) => w.flush(),
+            #[cfg(test)]
+            Output::Broken => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
+
+/// What components are drawn with, the terminal or an in-memory buffer in tests.
+pub enum Backend {
+    Crossterm(CrosstermBackend<Output>),
+    #[cfg(any(test, feature = "testing"))]
+    Test(TestBackend),
+}
+
+macro_rules! delegate {
+    ($self:ident, $b:ident => $e:expr) => {
+        match $self {
+            Backend::Crossterm($b) => $e,
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Test($b) => $e,
+        }
+    };
+}
+
+impl ratatui::backend::Backend for Backend {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        delegate!(self, b => b.draw(content))
+    }
+
+    fn append_lines(&mut self, n: u16) -> io::Result<()> {
+        delegate!(self, b => b.append_lines(n))
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        delegate!(self, b => b.hide_cursor())
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        delegate!(self, b => b.show_cursor())
+    }
+
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        delegate!(self, b => b.get_cursor())
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        delegate!(self, b => b.set_cursor(x, y))
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        delegate!(self, b => b.clear())
+    }
+
+    fn clear_region(&mut self, clear_type: ClearType) -> io::Result<()> {
+        delegate!(self, b => b.clear_region(clear_type))
+    }
+
+    fn size(&self) -> io::Result<Rect> {
+        delegate!(self, b => b.size())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        delegate!(self, b => ratatui::backend::Backend::flush(b))
+    }
+}
 
-pub type Frame<'a> = ratatui::Frame<'a, Backend<std::io::Stderr>>;
+/// Lets escape sequences be written straight to the terminal, they are dropped in tests.
+impl Write for Backend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Backend::Crossterm(b) => b.write(buf),
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Test(_) => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Backend::Crossterm(b) => Write::flush(b),
+            #[cfg(any(test, feature = "testing"))]
+            Backend::Test(_) => Ok(()),
+        }
+    }
+}
+
+/// Where the application is drawn.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Screen {
+    /// The alternate screen, bringing back what was on the terminal before on exit.
+    #[default]
+    Alternate,
+    /// A viewport of this many rows below the cursor, left in the scrollback on exit.
+    Inline(u16),
+}
 
 pub struct Tui {
-    terminal: Terminal<Backend<std::io::Stderr>>,
+    terminal: Terminal<Backend>,
+    screen: Screen,
+    target: Target,
+    active: bool,
 }
 
 impl Drop for Tui {
     fn drop(&mut self) {
-        self.exit().expect("Failed to cleanup terminal.");
+        // Whatever failed on the way here was reported already, it may well fail again
+        let _ = self.exit(None);
     }
 }
 
 impl Tui {
-    pub fn new() -> Result<Self> {
-        Ok(Self { terminal: Terminal::new(Backend::new(std::io::stderr()))? })
+    pub fn new(screen: Screen, target: Target) -> Result<Self> {
+        let viewport = match screen {
+            Screen::Alternate => Viewport::Fullscreen,
+            Screen::Inline(height) => Viewport::Inline(height),
+        };
+        let backend = Backend::Crossterm(CrosstermBackend::new(target.open()?));
+        let terminal = Terminal::with_options(backend, TerminalOptions { viewport })?;
+        Ok(Self {
+            terminal,
+            screen,
+            target,
+            active: false,
+        })
     }
 
-    pub fn enter(&self) -> Result<()> {
+    pub fn enter(&mut self) -> Result<()> {
         crossterm::terminal::enable_raw_mode()?;
+        let out = self.terminal.backend_mut();
+        if self.screen == Screen::Alternate {
+            execute!(out, EnterAlternateScreen)?;
+        }
+        self.active = true;
         Ok(execute!(
-            std::io::stderr(),
-            EnterAlternateScreen,
+            out,
             EnableMouseCapture,
+            EnableBracketedPaste,
             cursor::Hide
         )?)
     }
 
-    pub fn exit(&self) -> Result<()> {
+    /// Restores the terminal. An inline viewport is left in the scrollback as it was last drawn,
+    /// or replaced by `summary`.
+    pub fn exit(&mut self, summary: Option<String>) -> Result<()> {
+        if !std::mem::take(&mut self.active) {
+            return Ok(());
+        }
+        if let Screen::Inline(_) = self.screen {
+            match summary {
+                Some(summary) => {
+                    // Clearing leaves the cursor at the top of the viewport
+                    self.terminal.clear()?;
+                    execute!(self.terminal.backend_mut(), Print(summary), Print("\r\n"))?;
+                }
+                None => {
+                    let area = self.terminal.get_frame().size();
+                    self.terminal
+                        .set_cursor(0, area.bottom().saturating_sub(1))?;
+                    execute!(self.terminal.backend_mut(), Print("\r\n"))?;
+                }
+            }
+        }
+        Self::restore(self.screen, self.target)
+    }
+
+    /// Puts the terminal back into its normal state without a [`Tui`] at hand, e.g. when
+    /// panicking. An inline viewport and the cursor stay where they are.
+    pub fn restore(screen: Screen, target: Target) -> Result<()> {
+        let mut out = target.open()?;
+        if screen == Screen::Alternate {
+            execute!(out, LeaveAlternateScreen)?;
+        }
         execute!(
-            std::io::stderr(),
-            LeaveAlternateScreen,
+            out,
             DisableMouseCapture,
+            DisableBracketedPaste,
             cursor::Show
         )?;
         Ok(disable_raw_mode()?)
     }
 
-    pub fn suspend(&self) -> Result<()> {
-        self.exit()?;
+    pub fn suspend(&mut self) -> Result<()> {
+        if let Screen::Inline(_) = self.screen {
+            // Hand the lines back to the shell, the viewport is drawn anew below it on resume
+            self.terminal.clear()?;
+            self.active = false;
+            Self::restore(self.screen, self.target)?;
+        } else {
+            self.exit(None)?;
+        }
         #[cfg(not(windows))]
-        Ok(signal_hook::low_level::raise(signal_hook::consts::signal::SIGTSTP)?)
+        Ok(signal_hook::low_level::raise(
+            signal_hook::consts::signal::SIGTSTP,
+        )?)
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Message {
     Render,
+    Resume,
     Stop,
     Suspend,
 }
 
 pub struct TerminalHandler {
-    pub task: JoinHandle<()>,
+    pub task: JoinHandle<Result<()>>,
     tx: mpsc::UnboundedSender<Message>,
 }
 
 impl TerminalHandler {
-    pub fn new(home: Arc<Mutex<Base>>) -> Self {
-        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+    pub fn new<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static>(
+        home: Arc<Mutex<C>>,
+        screen: Screen,
+        target: Target,
+        dump: Option<ScreenDump>,
+    ) -> Self {
+        // Created ahead of the event handler, an inline viewport queries the cursor position which
+        // a running event stream would swallow
+        let tui = Tui::new(screen, target);
+        Self::spawn(home, dump, move || {
+            let mut t = tui.context(anyhow!("Unable to create terminal"))?;
+            t.enter().context("Unable to enter terminal")?;
+            Ok(t)
+        })
+    }
 
-        let task = tokio::spawn(async move {
-            let mut t = Tui::new()
-                .context(anyhow!("Unable to create terminal"))
-                .unwrap();
-            t.enter().unwrap();
+    /// Runs the task drawing `home` into the terminal handed over by `open`.
+    fn spawn<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static>(
+        home: Arc<Mutex<C>>,
+        dump: Option<ScreenDump>,
+        open: impl FnOnce() -> Result<Tui> + Send + 'static,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        let task: JoinHandle<Result<()>> = tokio::spawn(async move {
+            let mut t = open()?;
             loop {
                 match rx.recv().await {
-                    Some(Message::Stop) => {
-                        t.exit().unwrap_or_default();
+                    Some(Message::Stop) | None => {
+                        let summary = home.lock().await.summary();
+                        t.exit(summary).context("Unable to restore terminal")?;
                         break;
                     }
                     Some(Message::Suspend) => {
-                        t.suspend().unwrap_or_default();
+                        t.suspend().context("Unable to suspend terminal")?;
                         break;
                     }
+                    Some(Message::Resume) => {
+                        // The terminal may have been reset while stopped by someone else
+                        t.enter().context("Unable to enter terminal")?;
+                        t.terminal.clear().context("Unable to clear terminal")?;
+                    }
                     Some(Message::Render) => {
                         let mut h = home.lock().await;
-                        t.terminal.draw(|f| {
-                            h.render(f, f.size());
-                        })
-                        .unwrap();
+                        let frame = t
+                            .terminal
+                            .draw(|f| {
+                                h.render(f, f.size());
+                            })
+                            .context("Unable to draw to terminal")?;
+                        if let Some(dump) = &dump {
+                            *dump.lock().map_err(|_| anyhow!("Screen poisoned"))? =
+                                buffer_lines(frame.buffer);
+                        }
                     }
-                    None => {}
                 }
             }
+            Ok(())
         });
         Self { task, tx }
     }
@@ -111,7 +361,39 @@ impl TerminalHandler {
         Ok(self.tx.send(Message::Stop)?)
     }
 
+    pub fn resume(&self) -> Result<()> {
+        Ok(self.tx.send(Message::Resume)?)
+    }
+
     pub fn render(&self) -> Result<()> {
         Ok(self.tx.send(Message::Render)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::{testing::Counter, Task, TaskError};
+
+    #[tokio::test]
+    async fn a_failing_writer_fails_the_terminal_task() {
+        let backend = Backend::Crossterm(CrosstermBackend::new(Output::Broken));
+        let viewport = Viewport::Fixed(Rect::new(0, 0, 20, 3));
+        let tui = Tui {
+            terminal: Terminal::with_options(backend, TerminalOptions { viewport }).unwrap(),
+            screen: Screen::Inline(3),
+            target: Target::Stderr,
+            // Restoring the inline viewport on drop fails on the same writer
+            active: true,
+        };
+        let root = Arc::new(Mutex::new(Counter::default()));
+        let handler = TerminalHandler::spawn(root, None, move || Ok(tui));
+        handler.render().unwrap();
+
+        let error = TaskError::new(Task::Terminal, handler.task.await);
+        assert!(
+            matches!(error, TaskError::Failed(Task::Terminal, _)),
+            "{error:?}"
+        );
+    }
+}
@@ -0,0 +1,430 @@
+//! Drives an [`App`] without a terminal: events and actions are injected by hand, every one of them
+//! is processed before the call returns, and frames are drawn into ratatui's [`TestBackend`]. Tests
+//! that let time pass start with a paused [`tokio::time`] clock and move it with
+//! [`Harness::advance`]. Applications get it with the `testing` feature.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use futures::StreamExt;
+use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+use tokio::sync::{
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+    MappedMutexGuard, MutexGuard,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    buffer_lines, clock, components::Component, keymap::parse_keys, terminal::Backend, Action, App,
+    AppAction, AppMessage, Control, Event, Exit, Instant, Replay, Ticks,
+};
+
+#[cfg(test)]
+pub(crate) use fixture::{Count, Counter, Note};
+
+pub struct Harness<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static> {
+    app: App<M, A, C>,
+    terminal: Terminal<Backend>,
+    action_tx: UnboundedSender<Action<A>>,
+    action_rx: UnboundedReceiver<Action<A>>,
+    message_rx: UnboundedReceiver<M>,
+    timers: CancellationToken,
+    ticks: Ticks,
+}
+
+impl<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static> Drop for Harness<M, A, C> {
+    fn drop(&mut self) {
+        self.timers.cancel();
+    }
+}
+
+impl<M, A, C> Harness<M, A, C>
+where
+    M: AppMessage + DeserializeOwned,
+    A: AppAction,
+    C: Component<M, A> + Send + 'static,
+{
+    /// Opens a control socket at `path` into the harnessed app, its screen being the last frame
+    /// rendered.
+    pub fn control(&self, path: &Path) -> Result<Control> {
+        let parse = |value| Ok(serde_json::from_value(value)?);
+        self.app.bind_control(path, parse, &self.action_tx)
+    }
+}
+
+impl<M: AppMessage, A: AppAction, C: Component<M, A> + Send + 'static> Harness<M, A, C> {
+    /// An app around `root` with the default settings, drawn `width` by `height`.
+    pub async fn new(root: C, width: u16, height: u16) -> Self {
+        Self::with_app(App::builder(root).build(), width, height).await
+    }
+
+    pub async fn with_app(mut app: App<M, A, C>, width: u16, height: u16) -> Self {
+        let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let (message_tx, message_rx) = mpsc::unbounded_channel();
+        let timers = CancellationToken::new();
+        app.init(&action_tx, Some(message_tx), &timers)
+            .await
+            .unwrap();
+        let tick_rate = app.tick_rate();
+        let backend = Backend::Test(TestBackend::new(width, height));
+        let mut harness = Self {
+            app,
+            terminal: Terminal::new(backend).unwrap(),
+            action_tx,
+            action_rx,
+            message_rx,
+            timers,
+            ticks: Ticks::new(tick_rate),
+        };
+        harness.settle().await;
+        harness
+    }
+
+    /// Passes `event` through the components like the event handler would, recording it if the app
+    /// has a recorder, then settles.
+    pub async fn event(&mut self, event: Event) {
+        if let Some(recorder) = self.app.recorder() {
+            recorder.record(&event).unwrap();
+        }
+        let action = self.app.root().lock().await.handle_events(Some(event));
+        self.action_tx.send(action).unwrap();
+        self.settle().await;
+    }
+
+    pub async fn key(&mut self, code: KeyCode) {
+        self.event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+            .await;
+    }
+
+    /// Presses every key of a sequence in keymap notation, e.g. `/hi<Space>there<Enter>`,
+    /// settling after each one.
+    pub async fn keys(&mut self, keys: &str) {
+        for key in parse_keys(keys, &[]).unwrap() {
+            self.event(Event::Key(key)).await;
+        }
+    }
+
+    /// Delivers `message` as if the host had sent it, then settles.
+    pub async fn message(&mut self, message: M) {
+        let action = self.app.root().lock().await.handle_message(message);
+        self.action_tx.send(action).unwrap();
+        self.settle().await;
+    }
+
+    /// Pastes `text` as a single bracketed paste.
+    pub async fn paste(&mut self, text: &str) {
+        self.event(Event::Paste(text.into())).await;
+    }
+
+    /// Resizes the terminal to `width` by `height`, then reports it like the terminal would.
+    pub async fn resize(&mut self, width: u16, height: u16) {
+        match self.terminal.backend_mut() {
+            Backend::Test(backend) => backend.resize(width, height),
+            Backend::Crossterm(_) => unreachable!("the harness draws to a test backend"),
+        }
+        self.event(Event::Resize(width, height)).await;
+    }
+
+    /// Feeds every event of `replay` in at the pace it asks for, settling after each one.
+    pub async fn replay(&mut self, replay: Replay) {
+        let mut events = replay.spawn();
+        while let Some(event) = events.recv().await {
+            self.event(event).await;
+        }
+    }
+
+    /// Sends `action` as if a component had, then settles.
+    pub async fn action(&mut self, action: Action<A>) {
+        self.action_tx.send(action).unwrap();
+        self.settle().await;
+    }
+
+    /// Applies queued actions, and those they lead to, until none are left.
+    pub async fn settle(&mut self) {
+        while let Ok(action) = self.action_rx.try_recv() {
+            if let Some(action) = self.app.update(action).await {
+                self.action_tx.send(action).unwrap();
+            }
+        }
+        if self.app.exit().is_some() {
+            // As a quitting app does once its event handler stopped
+            self.app.close_sources().await;
+        }
+    }
+
+    /// Lets `duration` pass. Ticks, timers, jobs and external sources fire in the order they come
+    /// due and every action is applied at the time it is sent; on a paused clock none of it waits
+    /// in real time.
+    pub async fn advance(&mut self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let sources = self.app.sources().clone();
+        let mut sources = sources.lock().await;
+        loop {
+            tokio::select! {
+                biased;
+                Some(action) = self.action_rx.recv() => {
+                    if let Some(action) = self.app.update(action).await {
+                        self.action_tx.send(action).unwrap();
+                    }
+                }
+                tick = self.ticks.next() => {
+                    let action = self.app.root().lock().await.handle_events(Some(tick));
+                    self.action_tx.send(action).unwrap();
+                }
+                Some(external) = sources.next(), if !sources.is_empty() => {
+                    let event = Some(Event::External(external));
+                    let action = self.app.root().lock().await.handle_events(event);
+                    self.action_tx.send(action).unwrap();
+                }
+                _ = clock::sleep_until(until) => break,
+            }
+        }
+        drop(sources);
+        // Tasks woken at the very end still get to send their actions
+        tokio::task::yield_now().await;
+        self.settle().await;
+    }
+
+    /// The root component, to inspect its state.
+    pub fn root(&self) -> MappedMutexGuard<'_, C> {
+        let root = self
+            .app
+            .root()
+            .try_lock()
+            .expect("the root is only locked while an action is applied");
+        MutexGuard::map(root, |root| &mut **root)
+    }
+
+    /// The messages sent to the host since the last call.
+    pub fn messages(&mut self) -> Vec<M> {
+        std::iter::from_fn(|| self.message_rx.try_recv().ok()).collect()
+    }
+
+    /// How the app would end, `None` while it keeps running.
+    pub fn exit(&self) -> Option<Exit> {
+        self.app.exit()
+    }
+
+    /// Draws a frame and returns it.
+    pub async fn render(&mut self) -> &Buffer {
+        let mut root = self.app.root().lock().await;
+        self.terminal.draw(|f| root.render(f, f.size())).unwrap();
+        let buffer = match self.terminal.backend() {
+            Backend::Test(backend) => backend.buffer(),
+            Backend::Crossterm(_) => unreachable!("the harness draws to a test backend"),
+        };
+        *self.app.dump().lock().unwrap() = buffer_lines(buffer);
+        buffer
+    }
+
+    /// Draws a frame and returns where it put the cursor, `None` when it is hidden.
+    pub async fn cursor(&mut self) -> Option<(u16, u16)> {
+        // Out of every buffer, so still there afterwards only when the frame did not place it
+        const UNSET: (u16, u16) = (u16::MAX, u16::MAX);
+        self.terminal.set_cursor(UNSET.0, UNSET.1).unwrap();
+        self.render().await;
+        Some(self.terminal.get_cursor().unwrap()).filter(|&at| at != UNSET)
+    }
+
+    /// Draws a frame and returns its text, one string per row without trailing spaces.
+    pub async fn lines(&mut self) -> Vec<String> {
+        buffer_lines(self.render().await)
+    }
+
+    /// Compares a frame with `tests/snapshots/<name>.txt` in the package under test. With
+    /// `UPDATE_SNAPSHOTS` set the file is written instead.
+    pub async fn assert_snapshot(&mut self, name: &str) {
+        let actual = self.lines().await.join("\n") + "\n";
+        let root = std::env::var_os("CARGO_MANIFEST_DIR").expect("run by cargo test");
+        let path = PathBuf::from(root)
+            .join("tests")
+            .join("snapshots")
+            .join(format!("{name}.txt"));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+            panic!(
+                "{}: {e}, run with UPDATE_SNAPSHOTS=1 to create it",
+                path.display()
+            )
+        });
+        assert!(
+            actual == expected,
+            "frame differs from {}\n--- expected\n{expected}--- actual\n{actual}",
+            path.display()
+        );
+    }
+}
+
+/// A root for the runtime's own tests, with an action and a message type of its own.
+#[cfg(test)]
+mod fixture {
+    use std::str::FromStr;
+
+    use anyhow::{bail, Result};
+    use crossterm::event::{KeyCode, KeyEvent};
+    use ratatui::{layout::Rect, widgets::Paragraph};
+    use serde::{Deserialize, Serialize};
+    use tokio::sync::mpsc::UnboundedSender;
+
+    use crate::tui::{
+        keymap::{KeyMatcher, Step},
+        split_action, Action, AppAction, Component, Frame, KeyMap, Mode, ModeStack, Quit, Timers,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+    pub enum Count {
+        Increment(usize),
+        Notify(String),
+    }
+
+    impl FromStr for Count {
+        type Err = anyhow::Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            match split_action(s)? {
+                ("Increment", amount) => Ok(Count::Increment(amount.map_or(Ok(1), str::parse)?)),
+                ("Notify", Some(text)) => Ok(Count::Notify(text.into())),
+                _ => bail!("unknown action `{s}`"),
+            }
+        }
+    }
+
+    impl AppAction for Count {
+        fn with_count(self, count: usize) -> Self {
+            match self {
+                Count::Increment(i) => Count::Increment(i.saturating_mul(count)),
+                action => action,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Note {
+        Notify(String),
+        Quit,
+    }
+
+    impl Quit for Note {
+        fn quit() -> Self {
+            Note::Quit
+        }
+
+        fn is_quit(&self) -> bool {
+            matches!(self, Note::Quit)
+        }
+    }
+
+    /// Counts with `j`, types into its input in insert mode and shows the last notification.
+    pub struct Counter {
+        keys: KeyMatcher<Count>,
+        modes: ModeStack,
+        counter: usize,
+        input: String,
+        notification: Option<String>,
+        tx: Option<UnboundedSender<Action<Count>>>,
+    }
+
+    impl Default for Counter {
+        fn default() -> Self {
+            let keymap = KeyMap::defaults("[normal]\nj = \"Increment\"").unwrap();
+            Self {
+                keys: KeyMatcher::new(keymap),
+                modes: ModeStack::default(),
+                counter: 0,
+                input: String::new(),
+                notification: None,
+                tx: None,
+            }
+        }
+    }
+
+    impl Component<Note, Count> for Counter {
+        fn init(
+            &mut self,
+            tx: UnboundedSender<Action<Count>>,
+            _: Option<UnboundedSender<Note>>,
+            _: Timers<Count>,
+        ) -> Result<()> {
+            self.tx = Some(tx);
+            Ok(())
+        }
+
+        fn handle_key_events(&mut self, key: KeyEvent) -> Action<Count> {
+            let steps = self.keys.feed(&self.modes, key);
+            let mut actions: Vec<_> = steps
+                .into_iter()
+                .map(|step| match step {
+                    Step::Action(action) => action,
+                    Step::Key(
+                        Mode::Insert,
+                        KeyEvent {
+                            code: KeyCode::Char(c),
+                            ..
+                        },
+                    ) => {
+                        self.input.push(c);
+                        Action::Update
+                    }
+                    Step::Key(..) => Action::Noop,
+                })
+                .collect();
+            let last = actions.pop().unwrap_or(Action::Noop);
+            if let Some(tx) = &self.tx {
+                for action in actions {
+                    let _ = tx.send(action);
+                }
+            }
+            last
+        }
+
+        fn handle_message(&mut self, message: Note) -> Action<Count> {
+            match message {
+                Note::Notify(text) => Count::Notify(text).into(),
+                Note::Quit => Action::Quit,
+            }
+        }
+
+        fn dispatch(&mut self, action: Action<Count>) -> Option<Action<Count>> {
+            match action {
+                Action::App(Count::Increment(i)) => self.counter += i,
+                Action::App(Count::Notify(text)) => self.notification = Some(text),
+                Action::EnterInsert => {
+                    self.modes.push(Mode::Insert);
+                }
+                Action::EnterNormal => {
+                    self.modes.pop();
+                }
+                _ => (),
+            }
+            None
+        }
+
+        fn snapshot(&self) -> serde_json::Value {
+            serde_json::json!({
+                "counter": self.counter,
+                "modes": self.modes.iter().map(|m| m.name()).collect::<Vec<_>>(),
+                "input": self.input,
+            })
+        }
+
+        fn render(&mut self, f: &mut Frame, area: Rect) {
+            let text = format!(
+                "Counter: {}\n{}\n{}",
+                self.counter,
+                self.notification.as_deref().unwrap_or_default(),
+                self.input
+            );
+            f.render_widget(Paragraph::new(text), area);
+        }
+    }
+}
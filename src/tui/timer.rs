@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+use super::{interval_at, sleep_until, Action, AppAction, Instant};
+
+type Entries = Arc<Mutex<HashMap<String, (u64, JoinHandle<()>)>>>;
+
+/// Delayed and recurring actions started by a component, keyed by id. Starting a timer under an
+/// id already in use replaces it. Dropping the service, or the app quitting, cancels every timer
+/// it and its [scopes](Timers::scope) started.
+pub struct Timers<A> {
+    tx: UnboundedSender<Action<A>>,
+    token: CancellationToken,
+    entries: Entries,
+    generation: u64,
+}
+
+impl<A: AppAction> Timers<A> {
+    pub fn new(tx: UnboundedSender<Action<A>>, token: CancellationToken) -> Self {
+        Self {
+            tx,
+            token,
+            entries: Entries::default(),
+            generation: 0,
+        }
+    }
+
+    /// A separate set of timers for a child component, cancelled along with this one.
+    pub fn scope(&self) -> Self {
+        Self::new(self.tx.clone(), self.token.child_token())
+    }
+
+    /// Sends `action` once after `delay`.
+    pub fn after(&mut self, id: impl Into<String>, delay: Duration, action: Action<A>) {
+        self.start(id.into(), delay, None, action);
+    }
+
+    /// Sends `action` every `period`, starting one period from now.
+    pub fn every(&mut self, id: impl Into<String>, period: Duration, action: Action<A>) {
+        self.start(id.into(), period, Some(period), action);
+    }
+
+    /// Stops the timer with `id`, returning whether it was still active.
+    pub fn cancel(&mut self, id: &str) -> bool {
+        match self.entries().remove(id) {
+            Some((_, handle)) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_active(&self, id: &str) -> bool {
+        self.entries().contains_key(id)
+    }
+
+    /// Ids of the timers that have not fired or been cancelled yet.
+    pub fn active(&self) -> Vec<String> {
+        self.entries().keys().cloned().collect()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, (u64, JoinHandle<()>)>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn start(&mut self, id: String, delay: Duration, period: Option<Duration>, action: Action<A>) {
+        self.generation += 1;
+        let generation = self.generation;
+        let (tx, token, entries) = (self.tx.clone(), self.token.clone(), self.entries.clone());
+        let key = id.clone();
+
+        // Held until the handle is stored, so a timer firing right away still finds its entry
+        let mut guard = self.entries();
+        let handle = tokio::spawn(async move {
+            let start = Instant::now() + delay;
+            match period {
+                Some(period) => {
+                    let mut interval = interval_at(start, period);
+                    loop {
+                        tokio::select! {
+                            _ = token.cancelled() => break,
+                            _ = interval.tick() => {
+                                if tx.send(action.clone()).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                None => {
+                    tokio::select! {
+                        _ = token.cancelled() => {}
+                        _ = sleep_until(start) => {
+                            // Removed before sending so the receiver no longer sees it as active
+                            let mut entries = entries.lock().unwrap_or_else(|e| e.into_inner());
+                            if entries.get(&key).is_some_and(|(g, _)| *g == generation) {
+                                entries.remove(&key);
+                            }
+                            drop(entries);
+                            let _ = tx.send(action);
+                        }
+                    }
+                }
+            }
+        });
+        if let Some((_, old)) = guard.insert(id, (generation, handle)) {
+            old.abort();
+        }
+    }
+}
+
+impl<A> Drop for Timers<A> {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use super::*;
+    use crate::tui::{sleep, testing::Count};
+
+    fn timers() -> (Timers<Count>, UnboundedReceiver<Action<Count>>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Timers::new(tx, CancellationToken::new()), rx)
+    }
+
+    /// Lets `ms` pass, returning what the timers sent meanwhile.
+    async fn wait(rx: &mut UnboundedReceiver<Action<Count>>, ms: u64) -> Vec<Action<Count>> {
+        sleep(Duration::from_millis(ms)).await;
+        tokio::task::yield_now().await;
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn one_shots_fire_once_and_leave() {
+        let (mut timers, mut rx) = timers();
+        timers.after("once", Duration::from_secs(1), Action::Tick);
+        assert!(timers.is_active("once"));
+        assert!(wait(&mut rx, 999).await.is_empty());
+        assert_eq!(wait(&mut rx, 1).await, [Action::Tick]);
+        assert!(!timers.is_active("once"));
+        assert!(wait(&mut rx, 5000).await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recurring_timers_fire_every_period_until_cancelled() {
+        let (mut timers, mut rx) = timers();
+        timers.every("poll", Duration::from_secs(1), Action::Update);
+        assert!(wait(&mut rx, 500).await.is_empty());
+        assert_eq!(wait(&mut rx, 3000).await, vec![Action::Update; 3]);
+        assert_eq!(timers.active(), ["poll"]);
+
+        assert!(timers.cancel("poll"));
+        assert!(!timers.cancel("poll"));
+        assert!(wait(&mut rx, 3000).await.is_empty());
+        assert!(timers.active().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn starting_a_timer_under_the_same_id_replaces_it() {
+        let (mut timers, mut rx) = timers();
+        timers.after(
+            "note",
+            Duration::from_secs(1),
+            Action::App(Count::Increment(1)),
+        );
+        wait(&mut rx, 500).await;
+        timers.after(
+            "note",
+            Duration::from_secs(1),
+            Action::App(Count::Increment(2)),
+        );
+        assert!(wait(&mut rx, 900).await.is_empty());
+        assert_eq!(wait(&mut rx, 100).await, [Action::App(Count::Increment(2))]);
+        assert!(!timers.is_active("note"));
+
+        timers.every(
+            "note",
+            Duration::from_secs(1),
+            Action::App(Count::Increment(3)),
+        );
+        timers.after(
+            "note",
+            Duration::from_millis(1500),
+            Action::App(Count::Increment(4)),
+        );
+        assert_eq!(
+            wait(&mut rx, 5000).await,
+            [Action::App(Count::Increment(4))]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropping_the_service_cancels_its_timers_and_scopes() {
+        let (mut timers, mut rx) = timers();
+        let mut scope = timers.scope();
+        timers.every(
+            "parent",
+            Duration::from_secs(1),
+            Action::App(Count::Increment(1)),
+        );
+        scope.every(
+            "child",
+            Duration::from_secs(1),
+            Action::App(Count::Increment(2)),
+        );
+
+        drop(scope);
+        assert_eq!(
+            wait(&mut rx, 1500).await,
+            [Action::App(Count::Increment(1))]
+        );
+
+        let mut scope = timers.scope();
+        scope.after(
+            "child",
+            Duration::from_secs(1),
+            Action::App(Count::Increment(2)),
+        );
+        drop(timers);
+        assert!(wait(&mut rx, 5000).await.is_empty());
+    }
+}
@@ -1,11 +1,14 @@
 use better_panic::Settings;
 
-use super::Tui;
+use super::{Screen, Target, Tui};
 
-pub fn initialize_panic_handler() {
-    std::panic::set_hook(Box::new(|panic_info| {
-        if let Ok(t) = Tui::new() {
-            t.exit().unwrap();
+pub fn initialize_panic_handler(screen: Screen, target: Target) {
+    std::panic::set_hook(Box::new(move |panic_info| {
+        // Nothing to be done if the terminal is gone, the report still has to get out
+        let _ = Tui::restore(screen, target);
+        if let Screen::Inline(_) = screen {
+            // Start the report below the part of the viewport the cursor was left in
+            eprintln!();
         }
         Settings::auto()
             .most_recent_first(false)
@@ -15,10 +18,8 @@ pub fn initialize_panic_handler() {
     }));
 }
 
-pub fn version() -> String {
-    format!(
-        "{}\n\nAuthors: {}",
-        env!("CARGO_PKG_NAME"),
-        clap::crate_authors!(),
-    )
+/// The `--version` text of an application, e.g.
+/// `version(env!("CARGO_PKG_VERSION"), clap::crate_authors!())`.
+pub fn version(version: &str, authors: &str) -> String {
+    format!("{version}\n\nAuthors: {authors}")
 }